@@ -0,0 +1,188 @@
+//! Packing icons into sprite sheets, so that a modpack with thousands of icons
+//! doesn't need thousands of requests.
+//!
+//! All icons of an atlas have the same size, so they are laid out in a grid,
+//! row by row. A sheet is at most [`MAX_SHEET_SIZE`] pixels wide and high; if
+//! the icons don't fit, more sheets are added.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    path::Path,
+};
+
+use color_eyre::eyre::Error;
+use image::{
+    imageops::{
+        self,
+        FilterType,
+    },
+    ImageFormat,
+    RgbaImage,
+};
+use serde::Serialize;
+
+/// The maximum width and height of a sheet, in pixels.
+pub const MAX_SHEET_SIZE: u32 = 2048;
+
+/// The index of an atlas, mapping icon keys to their rectangles.
+#[derive(Debug, Serialize)]
+pub struct Atlas {
+    /// The width and height of each icon.
+    pub size: u32,
+
+    pub sheets: Vec<Sheet>,
+
+    /// The path of the stylesheet, relative to the output directory. It has a
+    /// class `factorio-icon-{size}-{id}` for each icon.
+    pub css: String,
+
+    pub icons: HashMap<String, AtlasIcon>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Sheet {
+    /// The path of the image, relative to the output directory.
+    pub file_name: String,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The position of an icon in an atlas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct AtlasIcon {
+    /// The number of the icon, used in the class names of the stylesheet.
+    pub id: usize,
+
+    /// The index of the sheet in [`Atlas::sheets`].
+    pub sheet: usize,
+
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Lays out `count` icons of the given size on sheets. Returns the positions of
+/// the icons and the width and height of each sheet.
+fn layout(count: usize, size: u32, max_sheet_size: u32) -> (Vec<AtlasIcon>, Vec<(u32, u32)>) {
+    // Sheets are square, so there are as many rows as columns.
+    let columns = (max_sheet_size / size).max(1) as usize;
+    let per_sheet = columns * columns;
+
+    let icons = (0..count)
+        .map(|id| {
+            let index = id % per_sheet;
+            AtlasIcon {
+                id,
+                sheet: id / per_sheet,
+                x: (index % columns) as u32 * size,
+                y: (index / columns) as u32 * size,
+            }
+        })
+        .collect();
+
+    let sheets = (0..count.div_ceil(per_sheet))
+        .map(|sheet| {
+            let count = (count - sheet * per_sheet).min(per_sheet);
+            let width = count.min(columns) as u32 * size;
+            let height = count.div_ceil(columns) as u32 * size;
+            (width, height)
+        })
+        .collect();
+
+    (icons, sheets)
+}
+
+/// Writes the sheets and the stylesheet of an atlas into `atlas/` of the
+/// output directory. The icons are scaled to `size`.
+pub fn write_atlas(
+    output: &Path,
+    size: u32,
+    icons: &[(String, RgbaImage)],
+) -> Result<Atlas, Error> {
+    let atlas_output = output.join("atlas");
+    if !atlas_output.exists() {
+        std::fs::create_dir(&atlas_output)?;
+    }
+
+    let (positions, sheet_sizes) = layout(icons.len(), size, MAX_SHEET_SIZE);
+    let mut sheet_images = sheet_sizes
+        .iter()
+        .map(|(width, height)| RgbaImage::new(*width, *height))
+        .collect::<Vec<_>>();
+
+    let css_name = format!("icons-{size}.css");
+    let mut css = format!(
+        ".factorio-icon-{size} {{ display: inline-block; width: {size}px; height: {size}px; background-repeat: no-repeat; }}\n"
+    );
+
+    let mut atlas_icons = HashMap::with_capacity(icons.len());
+    for ((key, image), position) in icons.iter().zip(&positions) {
+        let image = if image.dimensions() == (size, size) {
+            image.clone()
+        }
+        else {
+            imageops::resize(image, size, size, FilterType::Lanczos3)
+        };
+        imageops::replace(
+            &mut sheet_images[position.sheet],
+            &image,
+            i64::from(position.x),
+            i64::from(position.y),
+        );
+
+        writeln!(
+            css,
+            ".factorio-icon-{size}-{} {{ background-image: url(\"{size}-{}.png\"); background-position: -{}px -{}px; }}",
+            position.id, position.sheet, position.x, position.y
+        )?;
+
+        atlas_icons.insert(key.clone(), *position);
+    }
+
+    let mut sheets = Vec::with_capacity(sheet_images.len());
+    for (i, image) in sheet_images.into_iter().enumerate() {
+        let file_name = format!("{size}-{i}.png");
+        image.save_with_format(atlas_output.join(&file_name), ImageFormat::Png)?;
+        sheets.push(Sheet {
+            file_name: format!("atlas/{file_name}"),
+            width: image.width(),
+            height: image.height(),
+        });
+    }
+
+    std::fs::write(atlas_output.join(&css_name), css)?;
+
+    Ok(Atlas {
+        size,
+        sheets,
+        css: format!("atlas/{css_name}"),
+        icons: atlas_icons,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_lays_out_icons_on_sheets() {
+        // 2x2 icons per sheet.
+        let (icons, sheets) = layout(5, 32, 64);
+
+        assert_eq!(sheets, [(64, 64), (32, 32)]);
+        assert_eq!(
+            icons
+                .iter()
+                .map(|icon| (icon.sheet, icon.x, icon.y))
+                .collect::<Vec<_>>(),
+            [(0, 0, 0), (0, 32, 0), (0, 0, 32), (0, 32, 32), (1, 0, 0)]
+        );
+
+        let (icons, sheets) = layout(3, 32, 2048);
+        assert_eq!(sheets, [(96, 32)]);
+        assert_eq!(icons[2].id, 2);
+
+        assert_eq!(layout(0, 32, 2048), (vec![], vec![]));
+    }
+}
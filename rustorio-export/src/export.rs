@@ -1,42 +1,50 @@
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{
-        BufWriter,
-        Cursor,
+    collections::{
+        HashMap,
+        HashSet,
     },
+    fs::File,
+    io::BufWriter,
     path::Path,
 };
 
 use color_eyre::eyre::Error;
 use image::{
-    io::Reader as ImageReader,
     ImageFormat,
+    RgbaImage,
 };
 use rustorio_loader::Loader;
 use rustorio_prototype::{
-    achievement::AchievementPrototype,
-    item::ItemPrototype,
-    recipe::RecipePrototype,
     technology::TechnologyPrototype,
-    types::{
-        FileName,
-        IconSpecification,
-    },
-    HasPrototypes,
+    types::FileName,
+    AnyPrototype,
     Prototypes,
 };
 use serde::Serialize;
 
+use crate::{
+    atlas::{
+        self,
+        Atlas,
+    },
+    icon,
+};
+
 #[derive(Debug, Serialize)]
 struct Data<'a> {
     prototypes: &'a Prototypes,
-    icons: HashMap<&'a FileName, String>,
+    icons: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    atlases: Vec<Atlas>,
 }
 
+/// Exports the prototypes and their icons. If `atlas_sizes` is empty, each
+/// icon is written to its own file, otherwise the icons are packed into an
+/// atlas for each size.
 pub fn export(
     output: impl AsRef<Path>,
     pretty: bool,
+    atlas_sizes: &[u32],
     loader: &Loader,
     prototypes: &Prototypes,
 ) -> Result<(), Error> {
@@ -45,10 +53,25 @@ pub fn export(
         std::fs::create_dir_all(output)?;
     }
 
-    let icons = export_icons(output, loader, prototypes)?;
+    let images = composite_icons(loader, prototypes)?;
+
+    let mut icons = HashMap::new();
+    let mut atlases = vec![];
+    if atlas_sizes.is_empty() {
+        icons = export_icons(output, images)?;
+    }
+    else {
+        for size in atlas_sizes {
+            atlases.push(atlas::write_atlas(output, *size, &images)?);
+        }
+    }
 
     let output = BufWriter::new(File::create(output.join("data.json"))?);
-    let data = Data { prototypes, icons };
+    let data = Data {
+        prototypes,
+        icons,
+        atlases,
+    };
     if pretty {
         serde_json::to_writer_pretty(output, &data)?;
     }
@@ -59,109 +82,85 @@ pub fn export(
     Ok(())
 }
 
-fn export_icons<'a>(
-    output: &Path,
+/// Composites each distinct icon once, keyed by
+/// [`IconSpecification::key`](rustorio_prototype::types::IconSpecification::key).
+fn composite_icons(
     loader: &Loader,
-    prototypes: &'a Prototypes,
-) -> Result<HashMap<&'a FileName, String>, Error> {
-    let icons_output = output.join("icons");
-    if !icons_output.exists() {
-        std::fs::create_dir(&icons_output)?;
-    }
-
-    let mut icon_data = vec![];
-    prototypes.collect_icons(|icon_spec| {
-        let (file_name, icon_size, icon_mipmaps) = match icon_spec {
-            IconSpecification::Multiple {
-                icons,
-                icon_size,
-                icon_mipmaps,
-            } => {
-                let Some(icon) = icons.first()
-                else {
-                    return;
-                };
-                (&icon.icon, *icon_size, *icon_mipmaps)
-            }
-            IconSpecification::Single {
-                icon,
-                icon_size,
-                icon_mipmaps,
-            } => (icon, Some(*icon_size), *icon_mipmaps),
-            IconSpecification::None => return,
+    prototypes: &Prototypes,
+) -> Result<Vec<(String, RgbaImage)>, Error> {
+    // Sorted, so that the icons are numbered the same in each export.
+    let mut sorted_prototypes = prototypes.iter_any().collect::<Vec<_>>();
+    sorted_prototypes.sort_by_key(|prototype| (prototype.type_name(), prototype.name()));
+
+    let mut icon_specs = vec![];
+    for prototype in sorted_prototypes {
+        let Some(icon_spec) = prototype.icon_spec()
+        else {
+            continue;
         };
-        icon_data.push((file_name, icon_size, icon_mipmaps));
-    });
+        if let Some(key) = icon_spec.key() {
+            icon_specs.push((key, icon_spec, default_icon_size(prototype)));
+        }
+    }
 
-    let mut icons = HashMap::new();
-    for (file_name, icon_size, icon_mipmaps) in icon_data {
-        if !icons.contains_key(file_name) {
-            let icon_data = loader.read_file(&file_name)?;
-            let mut image_reader = ImageReader::new(Cursor::new(icon_data));
-
-            if let Some(ext) = file_name.as_path().extension().and_then(|s| s.to_str()) {
-                if let Some(format) = ImageFormat::from_extension(ext) {
-                    image_reader.set_format(format);
-                }
-            }
-            else {
-                image_reader = image_reader.with_guessed_format()?;
-            }
+    let mut images: HashMap<FileName, RgbaImage> = HashMap::new();
+    let mut keys = HashSet::new();
+    let mut icons = vec![];
+    for (key, icon_spec, default_size) in icon_specs {
+        if !keys.insert(key.clone()) {
+            continue;
+        }
 
-            log::debug!("{file_name}: size={icon_size:?}, mipmaps={icon_mipmaps}");
+        let layers = icon::layers(icon_spec, default_size);
+        log::debug!("{key}: {} layers", layers.len());
 
-            let image = image_reader.decode()?;
-            let image = if let Some(icon_size) = icon_size {
-                let icon_size = icon_size as u32;
-                image.crop_imm(0, 0, icon_size, icon_size)
+        let image = icon::composite(&layers, |file_name| {
+            if let Some(image) = images.get(file_name) {
+                return Ok(image.clone());
             }
-            else {
-                image
-            };
-
-            let output_name = format!("{}.png", icons.len());
-            image.save_with_format(icons_output.join(&output_name), ImageFormat::Png)?;
+            let image = icon::read_image(loader, file_name)?;
+            images.insert(file_name.clone(), image.clone());
+            Ok(image)
+        })?;
+        let Some(image) = image
+        else {
+            continue;
+        };
 
-            icons.insert(file_name, output_name);
-        }
+        icons.push((key, image));
     }
 
     Ok(icons)
 }
 
-trait CollectIcons {
-    fn collect_icons<'a, F: FnMut(&'a IconSpecification)>(&'a self, f: F);
-}
-
-impl CollectIcons for Prototypes {
-    fn collect_icons<'a, F: FnMut(&'a IconSpecification)>(&'a self, mut f: F) {
-        HasPrototypes::<AchievementPrototype>::iter(self).for_each(|p| p.collect_icons(&mut f));
-        HasPrototypes::<TechnologyPrototype>::iter(self).for_each(|p| p.collect_icons(&mut f));
-        HasPrototypes::<RecipePrototype>::iter(self).for_each(|p| p.collect_icons(&mut f));
-        HasPrototypes::<ItemPrototype>::iter(self).for_each(|p| p.collect_icons(&mut f));
+/// Exports one PNG per icon. The icons are mapped by their keys to their file
+/// names.
+fn export_icons(
+    output: &Path,
+    images: Vec<(String, RgbaImage)>,
+) -> Result<HashMap<String, String>, Error> {
+    let icons_output = output.join("icons");
+    if !icons_output.exists() {
+        std::fs::create_dir(&icons_output)?;
     }
-}
 
-impl CollectIcons for AchievementPrototype {
-    fn collect_icons<'a, F: FnMut(&'a IconSpecification)>(&'a self, mut f: F) {
-        f(&self.icon_spec);
+    let mut icons = HashMap::with_capacity(images.len());
+    for (key, image) in images {
+        let output_name = format!("{}.png", icons.len());
+        image.save_with_format(icons_output.join(&output_name), ImageFormat::Png)?;
+        icons.insert(key, output_name);
     }
-}
 
-impl CollectIcons for TechnologyPrototype {
-    fn collect_icons<'a, F: FnMut(&'a IconSpecification)>(&'a self, mut f: F) {
-        f(&self.icon_spec);
-    }
+    Ok(icons)
 }
 
-impl CollectIcons for RecipePrototype {
-    fn collect_icons<'a, F: FnMut(&'a IconSpecification)>(&'a self, mut f: F) {
-        f(&self.icon_spec);
+/// The size in which the plain versions of icons are shown, see
+/// [`icon::layers`].
+fn default_icon_size(prototype: &AnyPrototype) -> f64 {
+    if prototype.upcast::<TechnologyPrototype>().is_some() {
+        256.0
     }
-}
-
-impl CollectIcons for ItemPrototype {
-    fn collect_icons<'a, F: FnMut(&'a IconSpecification)>(&'a self, mut f: F) {
-        f(&self.icon_spec);
+    else {
+        32.0
     }
 }
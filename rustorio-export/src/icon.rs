@@ -0,0 +1,282 @@
+//! Compositing icons from their layers, like the game does.
+//!
+//! The first layer determines the size of the icon. The other layers are
+//! scaled and shifted relative to it, tinted, and blended over it with
+//! premultiplied alpha, so that a tint with an alpha of 0 blends additively.
+
+use std::io::Cursor;
+
+use color_eyre::eyre::Error;
+use image::{
+    imageops::{
+        self,
+        FilterType,
+    },
+    io::Reader as ImageReader,
+    ImageFormat,
+    Rgba,
+    RgbaImage,
+};
+use rustorio_loader::Loader;
+use rustorio_prototype::types::{
+    FileName,
+    IconSpecification,
+};
+
+/// A layer of an icon, with the defaults of the icon specification applied.
+#[derive(Clone, Debug)]
+pub struct IconLayer<'a> {
+    pub file_name: &'a FileName,
+
+    /// The size of the largest mipmap.
+    pub icon_size: u32,
+
+    pub mipmaps: u8,
+    pub tint: [f32; 4],
+    pub shift: [f32; 2],
+    pub scale: f64,
+}
+
+/// The layers of an icon. `default_size` is the size in which plain icons are
+/// shown, which determines the default scale of layers: 32 for most
+/// prototypes and 256 for technologies.
+pub fn layers(icon_spec: &IconSpecification, default_size: f64) -> Vec<IconLayer<'_>> {
+    match icon_spec {
+        IconSpecification::Multiple {
+            icons,
+            icon_size,
+            icon_mipmaps,
+        } => {
+            icons
+                .iter()
+                .filter_map(|icon| {
+                    let size = icon.icon_size.or(*icon_size)?.max(1) as u32;
+                    let mipmaps = if icon.icon_mipmaps > 0 {
+                        icon.icon_mipmaps
+                    }
+                    else {
+                        *icon_mipmaps
+                    };
+                    let tint = &icon.tint;
+                    Some(IconLayer {
+                        file_name: &icon.icon,
+                        icon_size: size,
+                        mipmaps,
+                        tint: [
+                            tint.color.red,
+                            tint.color.green,
+                            tint.color.blue,
+                            tint.alpha,
+                        ],
+                        shift: [icon.shift.x, icon.shift.y],
+                        scale: icon.scale.unwrap_or_else(|| default_size / f64::from(size)),
+                    })
+                })
+                .collect()
+        }
+        IconSpecification::Single {
+            icon,
+            icon_size,
+            icon_mipmaps,
+        } => {
+            let size = (*icon_size).max(1) as u32;
+            vec![IconLayer {
+                file_name: icon,
+                icon_size: size,
+                mipmaps: *icon_mipmaps,
+                tint: [1.0; 4],
+                shift: [0.0; 2],
+                scale: default_size / f64::from(size),
+            }]
+        }
+        IconSpecification::None => vec![],
+    }
+}
+
+/// Reads an image file of the game.
+pub fn read_image(loader: &Loader, file_name: &FileName) -> Result<RgbaImage, Error> {
+    let data = loader.read_file(file_name)?;
+    let mut image_reader = ImageReader::new(Cursor::new(data));
+
+    if let Some(format) = file_name
+        .as_path()
+        .extension()
+        .and_then(|s| s.to_str())
+        .and_then(ImageFormat::from_extension)
+    {
+        image_reader.set_format(format);
+    }
+    else {
+        image_reader = image_reader.with_guessed_format()?;
+    }
+
+    Ok(image_reader.decode()?.to_rgba8())
+}
+
+/// Composites the layers of an icon. `read` returns the image of a file.
+pub fn composite<F>(layers: &[IconLayer], mut read: F) -> Result<Option<RgbaImage>, Error>
+where
+    F: FnMut(&FileName) -> Result<RgbaImage, Error>,
+{
+    let Some(first) = layers.first()
+    else {
+        return Ok(None);
+    };
+
+    let size = first.icon_size;
+    // The first layer is shown in its full size, so that one unit of its scale is
+    // `1 / scale` pixels.
+    let pixels_per_unit = 1.0 / first.scale;
+    let mut canvas = vec![[0.0f32; 4]; (size * size) as usize];
+
+    for layer in layers {
+        let target_size = f64::from(layer.icon_size) * layer.scale * pixels_per_unit;
+        let target_pixels = target_size.round().max(1.0) as u32;
+
+        let image = read(layer.file_name)?;
+        let image = mip_level(&image, layer, target_pixels);
+        let image = if image.dimensions() == (target_pixels, target_pixels) {
+            image
+        }
+        else {
+            imageops::resize(&image, target_pixels, target_pixels, FilterType::Triangle)
+        };
+
+        let center_x = f64::from(size) / 2.0 + f64::from(layer.shift[0]) * pixels_per_unit;
+        let center_y = f64::from(size) / 2.0 + f64::from(layer.shift[1]) * pixels_per_unit;
+        let left = (center_x - target_size / 2.0).round() as i64;
+        let top = (center_y - target_size / 2.0).round() as i64;
+
+        blend(&mut canvas, size, &image, left, top, layer.tint);
+    }
+
+    Ok(Some(RgbaImage::from_fn(size, size, |x, y| {
+        let [r, g, b, a] = canvas[(y * size + x) as usize];
+        let unpremultiply = |c: f32| {
+            if a > 0.0 {
+                (c / a * 255.0).clamp(0.0, 255.0).round() as u8
+            }
+            else {
+                0
+            }
+        };
+        Rgba([
+            unpremultiply(r),
+            unpremultiply(g),
+            unpremultiply(b),
+            (a * 255.0).clamp(0.0, 255.0).round() as u8,
+        ])
+    })))
+}
+
+/// The smallest mipmap that is at least `target_size` large, or the largest
+/// one. Mipmaps are next to each other, each half the size of the previous one.
+fn mip_level(image: &RgbaImage, layer: &IconLayer, target_size: u32) -> RgbaImage {
+    let mut x = 0;
+    let mut level_size = layer.icon_size;
+    for _ in 1..layer.mipmaps.max(1) {
+        let next_size = level_size / 2;
+        if next_size < target_size || next_size == 0 || x + level_size + next_size > image.width() {
+            break;
+        }
+        x += level_size;
+        level_size = next_size;
+    }
+
+    let width = level_size.min(image.width().saturating_sub(x));
+    let height = level_size.min(image.height());
+    imageops::crop_imm(image, x, 0, width, height).to_image()
+}
+
+/// Blends a tinted image over the premultiplied canvas.
+fn blend(
+    canvas: &mut [[f32; 4]],
+    size: u32,
+    image: &RgbaImage,
+    left: i64,
+    top: i64,
+    tint: [f32; 4],
+) {
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let canvas_x = left + i64::from(x);
+        let canvas_y = top + i64::from(y);
+        if canvas_x < 0
+            || canvas_y < 0
+            || canvas_x >= i64::from(size)
+            || canvas_y >= i64::from(size)
+        {
+            continue;
+        }
+
+        let [r, g, b, a] = pixel.0.map(|c| f32::from(c) / 255.0);
+        let alpha = a * tint[3];
+        let source = [r * a * tint[0], g * a * tint[1], b * a * tint[2]];
+
+        let target = &mut canvas[(canvas_y * i64::from(size) + canvas_x) as usize];
+        for i in 0..3 {
+            target[i] = source[i] + target[i] * (1.0 - alpha);
+        }
+        target[3] = alpha + target[3] * (1.0 - alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_composites_tinted_and_scaled_layers() {
+        let base = FileName::from("base.png".to_owned());
+        let overlay = FileName::from("overlay.png".to_owned());
+
+        // A 4x4 icon with a 2x2 mipmap, the mipmap is red to tell it apart.
+        let mut base_image = RgbaImage::from_pixel(6, 4, Rgba([255, 255, 255, 255]));
+        for x in 4..6 {
+            for y in 0..2 {
+                base_image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let overlay_image = RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]));
+
+        let layers = [
+            IconLayer {
+                file_name: &base,
+                icon_size: 4,
+                mipmaps: 2,
+                tint: [1.0; 4],
+                shift: [0.0; 2],
+                scale: 8.0,
+            },
+            // Half as large as the first layer, shifted to the top left corner and
+            // tinted blue.
+            IconLayer {
+                file_name: &overlay,
+                icon_size: 2,
+                mipmaps: 0,
+                tint: [0.0, 0.0, 1.0, 1.0],
+                shift: [-8.0, -8.0],
+                scale: 8.0,
+            },
+        ];
+
+        let image = composite(&layers, |file_name| {
+            Ok(if *file_name == base {
+                base_image.clone()
+            }
+            else {
+                overlay_image.clone()
+            })
+        })
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(image.dimensions(), (4, 4));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(1, 1), &Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(3, 3), &Rgba([255, 255, 255, 255]));
+
+        let small = mip_level(&base_image, &layers[0], 2);
+        assert_eq!(small.dimensions(), (2, 2));
+        assert_eq!(small.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+    }
+}
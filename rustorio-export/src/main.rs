@@ -1,38 +1,91 @@
+mod atlas;
 mod export;
+mod icon;
 
 use std::path::PathBuf;
 
-use color_eyre::eyre::Error;
-use rustorio_loader::Loader;
+use color_eyre::eyre::{
+    eyre,
+    Error,
+};
+use rustorio_loader::{
+    cache::default_cache_dir,
+    discovery,
+    dump::read_data_raw_dump,
+};
 use rustorio_prototype::Prototypes;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 pub struct Args {
+    /// The game's `data` directory. Defaults to the one of the newest Factorio
+    /// installation that can be found.
     #[structopt(long, env = "FACTORIO_DATA")]
-    data_dir: PathBuf,
+    data_dir: Option<PathBuf>,
 
     #[structopt(long, env = "FACTORIO_MODS")]
     mod_dir: Option<PathBuf>,
 
+    /// Directory in which the results of the data stage are cached. Defaults
+    /// to `~/.cache/rustorio`.
+    #[structopt(long, env = "RUSTORIO_CACHE")]
+    cache_dir: Option<PathBuf>,
+
+    /// Always run the data stage, without using the cache.
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// Load the prototypes from a `data-raw-dump.json` written by `factorio
+    /// --dump-data`, instead of running the data stage.
+    #[structopt(long)]
+    data_raw_dump: Option<PathBuf>,
+
+    /// Continue the data stage if a mod raises an error, skipping the rest of
+    /// that mod. The results are not cached.
+    #[structopt(long)]
+    skip_failed_mods: bool,
+
     #[structopt(short, long)]
     output: PathBuf,
 
     #[structopt(short, long)]
     pretty: bool,
+
+    /// Pack the icons into sprite sheets with icons of this size, e.g. 32 or
+    /// 64, instead of writing one file per icon. Can be given more than once.
+    #[structopt(long = "atlas-size")]
+    atlas_sizes: Vec<u32>,
 }
 
 impl Args {
     fn run(self) -> Result<(), Error> {
-        let loader = if let Some(mod_dir) = &self.mod_dir {
-            Loader::modded(&self.data_dir, mod_dir)?
+        if self.atlas_sizes.contains(&0) {
+            return Err(eyre!("The atlas size must be larger than 0."));
         }
-        else {
-            Loader::vanilla(&self.data_dir)?
+
+        let loader =
+            discovery::builder(self.data_dir.as_deref(), self.mod_dir.as_deref())?.finish()?;
+        let cache_dir = self.cache_dir.or_else(default_cache_dir);
+        let prototypes: Prototypes = match (&self.data_raw_dump, cache_dir) {
+            (Some(path), _) => read_data_raw_dump(path)?,
+            (None, _) if self.skip_failed_mods => {
+                let (prototypes, errors) = loader.data_stage_tolerant()?;
+                for error in errors {
+                    log::error!("{}", error);
+                }
+                prototypes
+            }
+            (None, Some(cache_dir)) if !self.no_cache => loader.data_stage_cached(cache_dir)?,
+            _ => loader.data_stage()?,
         };
-        let prototypes: Prototypes = loader.data_stage()?;
 
-        export::export(&self.output, self.pretty, &loader, &prototypes)?;
+        export::export(
+            &self.output,
+            self.pretty,
+            &self.atlas_sizes,
+            &loader,
+            &prototypes,
+        )?;
 
         Ok(())
     }
@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+        Read,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use rustorio_lua_api::FromLuaValue;
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::{
+    files::ModFiles,
+    settings::SettingType,
+    Error,
+    Loader,
+};
+
+/// Bump this when the way the data stage is run changes, so that old cache
+/// entries are not used anymore.
+const CACHE_VERSION: u32 = 2;
+
+/// File types that can influence the result of the data stage. Graphics and
+/// sounds only matter once they're loaded by other tools.
+const HASHED_EXTENSIONS: &[&str] = &["lua", "json", "cfg"];
+
+/// Returns the default directory for cached data stage results:
+/// `$XDG_CACHE_HOME/rustorio` or `~/.cache/rustorio`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .map(|dir| dir.join("rustorio"))
+}
+
+impl Loader {
+    /// Computes a hash over everything that determines the result of the data
+    /// stage: The core files, the names, versions and contents of the loaded
+    /// mods in load order, and the startup settings chosen by the user.
+    pub fn mod_set_hash(&self) -> Result<String, Error> {
+        let mut hasher = Sha256::new();
+
+        hasher.update(CACHE_VERSION.to_le_bytes());
+        hasher.update(env!("CARGO_PKG_VERSION"));
+
+        hasher.update(b"core\0");
+        hash_files(&mut hasher, self.scopes.core_files())?;
+
+        for fmod in self.mods.iter() {
+            hasher.update(fmod.name());
+            hasher.update([0]);
+            hasher.update(fmod.version().to_string());
+            hasher.update([0]);
+
+            hash_files(&mut hasher, &fmod.files)?;
+        }
+
+        if let Some(settings) = &self.settings {
+            let mut startup = settings
+                .section(SettingType::Startup)
+                .iter()
+                .collect::<Vec<_>>();
+            startup.sort_by_key(|(name, _)| *name);
+            for (name, value) in startup {
+                hasher.update(format!("{}={:?}\0", name, value));
+            }
+        }
+
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    /// Like [`Loader::data_stage`], but stores the result in `cache_dir`, and
+    /// reuses it if the mod set hasn't changed since. Unreadable cache entries
+    /// are ignored and replaced.
+    pub fn data_stage_cached<T>(&self, cache_dir: impl AsRef<Path>) -> Result<T, Error>
+    where
+        T: FromLuaValue + Serialize + DeserializeOwned,
+    {
+        let cache_dir = cache_dir.as_ref();
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.mod_set_hash()?);
+        hasher.update(std::any::type_name::<T>());
+        let key = hasher
+            .finalize()
+            .iter()
+            .take(16)
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let path = cache_dir.join(format!("{}.json", key));
+
+        if path.exists() {
+            match read_cache_entry(&path) {
+                Ok(data) => {
+                    log::info!("Using cached data stage: {}", path.display());
+                    return Ok(data);
+                }
+                Err(e) => {
+                    log::warn!("Ignoring cached data stage {}: {}", path.display(), e);
+                }
+            }
+        }
+
+        let data: T = self.data_stage()?;
+
+        std::fs::create_dir_all(cache_dir)?;
+        // Write to a temporary file first, so that an interrupted write doesn't leave a
+        // truncated cache entry behind.
+        let tmp_path = path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, &data)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&tmp_path, &path)?;
+        log::info!("Cached data stage: {}", path.display());
+
+        Ok(data)
+    }
+}
+
+fn read_cache_entry<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+fn hash_files(hasher: &mut Sha256, files: &ModFiles) -> Result<(), Error> {
+    match files {
+        ModFiles::Direcory { path } => hash_dir(hasher, path)?,
+        ModFiles::Zip { path, .. } => hash_file(hasher, path)?,
+        ModFiles::Memory { files } => {
+            for (path, data) in files {
+                hasher.update(path.to_string_lossy().as_bytes());
+                hasher.update([0]);
+                hasher.update(data);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(hasher: &mut Sha256, path: &Path) -> Result<(), Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = [0; 0x10000];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(())
+}
+
+/// Hashes the relative paths and contents of all relevant files in a
+/// directory, in a stable order.
+fn hash_dir(hasher: &mut Sha256, dir: &Path) -> Result<(), Error> {
+    let mut files = vec![];
+    collect_files(dir, &mut files)?;
+    files.sort();
+
+    for path in files {
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hash_file(hasher, &path)?;
+    }
+
+    Ok(())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in dir.read_dir()? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, files)?;
+        }
+        else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| HASHED_EXTENSIONS.contains(&ext))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
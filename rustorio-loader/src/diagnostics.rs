@@ -0,0 +1,103 @@
+use std::fmt::{
+    self,
+    Display,
+    Formatter,
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref LOCATION_REGEX: Regex = Regex::new(r"^([^:\n]+):(\d+): ").unwrap();
+}
+
+/// An error raised while running one of a mod's files.
+#[derive(Clone, Debug)]
+pub struct ModError {
+    /// The mod whose file failed, or `core`.
+    pub mod_name: String,
+
+    /// The file that was run, e.g. `__foo__/data-updates.lua`.
+    pub file: String,
+
+    /// Where the error was raised, e.g. `__foo__/prototypes/item.lua` and the
+    /// line in it. This can be in a different file than [`ModError::file`]
+    /// and even a different mod, if the error was raised by a library.
+    pub location: Option<(String, u32)>,
+
+    /// The error message, without the location.
+    pub message: String,
+
+    /// The Lua stack traceback at the point the error was raised. Syntax errors
+    /// don't have one.
+    pub traceback: Option<String>,
+}
+
+impl ModError {
+    pub fn new(
+        mod_name: impl Into<String>,
+        file: impl Into<String>,
+        message: impl Into<String>,
+        traceback: Option<String>,
+    ) -> Self {
+        let mut message = message.into();
+
+        let location = LOCATION_REGEX.captures(&message).and_then(|captures| {
+            let line = captures[2].parse().ok()?;
+            Some((captures[1].to_owned(), line, captures[0].len()))
+        });
+        let location = location.map(|(source, line, prefix_len)| {
+            message.drain(..prefix_len);
+            (source, line)
+        });
+
+        Self {
+            mod_name: mod_name.into(),
+            file: file.into(),
+            location,
+            message,
+            traceback,
+        }
+    }
+}
+
+impl Display for ModError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Error in mod {} while running {}",
+            self.mod_name, self.file
+        )?;
+        if let Some((source, line)) = &self.location {
+            write!(f, " at {}:{}", source, line)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(traceback) = &self.traceback {
+            write!(f, "\n{}", traceback)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ModError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_the_error_location() {
+        let error = ModError::new(
+            "foo",
+            "__foo__/data.lua",
+            "__foo__/prototypes/item.lua:12: attempt to index a nil value",
+            None,
+        );
+
+        assert_eq!(
+            error.location,
+            Some(("__foo__/prototypes/item.lua".to_owned(), 12))
+        );
+        assert_eq!(error.message, "attempt to index a nil value");
+    }
+}
@@ -0,0 +1,355 @@
+//! Finding Factorio installations in the usual places on Linux.
+
+use std::{
+    collections::HashMap,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    Builder,
+    Error,
+    Version,
+};
+
+lazy_static! {
+    static ref LIBRARY_PATH_REGEX: Regex = Regex::new(r#""path"\s+"([^"]+)""#).unwrap();
+}
+
+/// A Factorio installation.
+#[derive(Clone, Debug)]
+pub struct Installation {
+    /// The installation directory, e.g.
+    /// `~/.steam/steam/steamapps/common/Factorio`.
+    pub path: PathBuf,
+
+    /// The directory containing `core` and `base`.
+    pub data_dir: PathBuf,
+
+    /// The directory in which the game stores mods, saves and settings.
+    pub write_data_dir: PathBuf,
+
+    /// The game's version, from `base/info.json`.
+    pub version: Version,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaseInfoJson {
+    version: String,
+}
+
+impl Installation {
+    /// Reads an installation from its directory. The write data directory is
+    /// determined from `config-path.cfg` and `config.ini` like the game does.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+
+        let use_system_dirs;
+        let config_dir;
+        if let Some(config_path) = read_config(&path.join("config-path.cfg"))? {
+            use_system_dirs = config_path
+                .get("use-system-read-write-data-directories")
+                .map(String::as_str)
+                != Some("false");
+            config_dir = config_path
+                .get("config-path")
+                .map(|value| resolve_path(value, &path, use_system_dirs));
+        }
+        else {
+            use_system_dirs = true;
+            config_dir = None;
+        }
+
+        let config_dir = config_dir.unwrap_or_else(|| {
+            if use_system_dirs {
+                system_write_data_dir().join("config")
+            }
+            else {
+                path.join("config")
+            }
+        });
+        let config = read_config(&config_dir.join("config.ini"))?.unwrap_or_default();
+
+        let data_dir = config
+            .get("path.read-data")
+            .map(|value| resolve_path(value, &path, use_system_dirs))
+            .filter(|dir| dir.join("base").join("info.json").exists())
+            .or_else(|| find_data_dir(&path))
+            .ok_or_else(|| Error::FileNotFound(path.join("data").join("base").join("info.json")))?;
+
+        let write_data_dir = config
+            .get("path.write-data")
+            .map(|value| resolve_path(value, &path, use_system_dirs))
+            .unwrap_or_else(|| {
+                if use_system_dirs {
+                    system_write_data_dir()
+                }
+                else {
+                    path.clone()
+                }
+            });
+
+        let info: BaseInfoJson =
+            serde_json::from_slice(&std::fs::read(data_dir.join("base").join("info.json"))?)?;
+        let version = info.version.parse()?;
+
+        Ok(Self {
+            path,
+            data_dir,
+            write_data_dir,
+            version,
+        })
+    }
+
+    pub fn mod_dir(&self) -> PathBuf {
+        self.write_data_dir.join("mods")
+    }
+
+    /// Creates a [`Builder`] with `core`, `base` and the mods in the
+    /// installation's mod directory.
+    pub fn builder(&self) -> Result<Builder, Error> {
+        let mut builder = Builder::from_data_dir(&self.data_dir)?;
+        let mod_dir = self.mod_dir();
+        if mod_dir.is_dir() {
+            builder.add_mod_dir(mod_dir)?;
+        }
+        Ok(builder)
+    }
+}
+
+/// Finds all Factorio installations in the usual places: Steam libraries,
+/// standalone installations in the home directory, `/opt` and `/usr/share`.
+/// Installations are ordered by version, newest first.
+pub fn find_installations() -> Vec<Installation> {
+    let mut installations: Vec<Installation> = vec![];
+
+    for path in candidate_paths() {
+        if !path.is_dir() {
+            continue;
+        }
+        match Installation::open(&path) {
+            Ok(installation) => {
+                let canonical = std::fs::canonicalize(&installation.path).ok();
+                let is_duplicate = installations.iter().any(|other| {
+                    canonical.is_some() && std::fs::canonicalize(&other.path).ok() == canonical
+                });
+                if !is_duplicate {
+                    log::info!(
+                        "Found Factorio {} at {}",
+                        installation.version,
+                        installation.path.display()
+                    );
+                    installations.push(installation);
+                }
+            }
+            Err(e) => log::debug!("No Factorio installation at {}: {}", path.display(), e),
+        }
+    }
+
+    installations.sort_by_key(|installation| std::cmp::Reverse(installation.version));
+    installations
+}
+
+/// Returns the newest installation, see [`find_installations`].
+pub fn find_installation() -> Option<Installation> {
+    find_installations().into_iter().next()
+}
+
+/// Creates a [`Builder`] with the given data and mod directories. Without a
+/// data directory, the one of the newest installation is used, and its mod
+/// directory if none is given.
+pub fn builder(data_dir: Option<&Path>, mod_dir: Option<&Path>) -> Result<Builder, Error> {
+    let mut builder = match (data_dir, mod_dir) {
+        (Some(data_dir), _) => Builder::from_data_dir(data_dir)?,
+        (None, Some(_)) => {
+            let installation = find_installation().ok_or(Error::InstallationNotFound)?;
+            Builder::from_data_dir(&installation.data_dir)?
+        }
+        (None, None) => {
+            return find_installation()
+                .ok_or(Error::InstallationNotFound)?
+                .builder();
+        }
+    };
+    if let Some(mod_dir) = mod_dir {
+        builder.add_mod_dir(mod_dir)?;
+    }
+    Ok(builder)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `~/.factorio`, where the game stores its data if it uses the system
+/// directories.
+fn system_write_data_dir() -> PathBuf {
+    home_dir().unwrap_or_default().join(".factorio")
+}
+
+fn candidate_paths() -> Vec<PathBuf> {
+    let mut paths = vec![];
+
+    if let Some(home) = home_dir() {
+        let steam_dirs = [
+            home.join(".steam").join("steam"),
+            home.join(".local").join("share").join("Steam"),
+            home.join(".var")
+                .join("app")
+                .join("com.valvesoftware.Steam")
+                .join(".local")
+                .join("share")
+                .join("Steam"),
+        ];
+        for steam_dir in steam_dirs {
+            for library in steam_libraries(&steam_dir) {
+                paths.push(library.join("steamapps").join("common").join("Factorio"));
+            }
+        }
+
+        paths.push(home.join("factorio"));
+        paths.push(home.join("Games").join("factorio"));
+    }
+
+    paths.push("/opt/factorio".into());
+    paths.push("/usr/share/factorio".into());
+
+    paths
+}
+
+/// The Steam library folders, including Steam's own directory.
+fn steam_libraries(steam_dir: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![steam_dir.to_owned()];
+
+    let Ok(vdf) = std::fs::read_to_string(steam_dir.join("steamapps").join("libraryfolders.vdf"))
+    else {
+        return libraries;
+    };
+
+    for captures in LIBRARY_PATH_REGEX.captures_iter(&vdf) {
+        let library = PathBuf::from(captures[1].replace("\\\\", "\\"));
+        if !libraries.contains(&library) {
+            libraries.push(library);
+        }
+    }
+
+    libraries
+}
+
+/// The data directory is usually `data` in the installation, but some
+/// distribution packages put `core` and `base` directly into it.
+fn find_data_dir(path: &Path) -> Option<PathBuf> {
+    [path.join("data"), path.to_owned()]
+        .into_iter()
+        .find(|dir| dir.join("base").join("info.json").exists())
+}
+
+/// Reads `key=value` pairs of an ini-like file. Keys in sections are prefixed
+/// with the section name, e.g. `path.write-data`. Returns `None` if the file
+/// doesn't exist.
+fn read_config(path: &Path) -> Result<Option<HashMap<String, String>>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut config = HashMap::new();
+    let mut section = None;
+    for line in std::fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = Some(name.to_owned());
+        }
+        else if let Some((key, value)) = line.split_once('=') {
+            let key = match &section {
+                Some(section) => format!("{}.{}", section, key.trim()),
+                None => key.trim().to_owned(),
+            };
+            config.insert(key, value.trim().to_owned());
+        }
+    }
+
+    Ok(Some(config))
+}
+
+/// Replaces the placeholders the game uses in `config-path.cfg` and
+/// `config.ini`, and removes `..` components.
+fn resolve_path(value: &str, installation: &Path, use_system_dirs: bool) -> PathBuf {
+    let executable_dir = installation.join("bin").join("x64");
+    let system_read_data = if use_system_dirs {
+        find_data_dir(installation).unwrap_or_else(|| installation.join("data"))
+    }
+    else {
+        installation.join("data")
+    };
+
+    let value = value
+        .replace("__PATH__executable__", &executable_dir.to_string_lossy())
+        .replace(
+            "__PATH__system-write-data__",
+            &system_write_data_dir().to_string_lossy(),
+        )
+        .replace(
+            "__PATH__system-read-data__",
+            &system_read_data.to_string_lossy(),
+        );
+
+    let mut path = PathBuf::new();
+    for component in Path::new(&value).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_a_standalone_installation() {
+        let path =
+            std::env::temp_dir().join(format!("rustorio-discovery-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(path.join("data").join("base")).unwrap();
+        std::fs::create_dir_all(path.join("config")).unwrap();
+        std::fs::write(
+            path.join("data").join("base").join("info.json"),
+            r#"{"name": "base", "version": "1.1.104", "title": "Base Mod", "author": "Factorio team"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            path.join("config-path.cfg"),
+            "config-path=__PATH__executable__/../../config\nuse-system-read-write-data-directories=false\n",
+        )
+        .unwrap();
+        std::fs::write(
+            path.join("config").join("config.ini"),
+            "; version=11\n[path]\nread-data=__PATH__executable__/../../data\nwrite-data=__PATH__executable__/../..\n\n[general]\nlocale=\n",
+        )
+        .unwrap();
+
+        let installation = Installation::open(&path).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(installation.version, Version::new(1, 1, 104));
+        assert_eq!(installation.data_dir, path.join("data"));
+        assert_eq!(installation.mod_dir(), path.join("mods"));
+    }
+}
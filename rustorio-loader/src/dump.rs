@@ -0,0 +1,138 @@
+//! Loading `data.raw` from a `data-raw-dump.json`, which the game writes to
+//! `script-output` when started with `--dump-data`.
+//!
+//! The JSON is converted back into Lua tables, so that the same
+//! [`FromLuaValue`] implementations are used as for [`Loader::data_stage`].
+//! This makes it possible to load mods that can't be run by our Lua
+//! environment, and to compare the results of our data stage with the game's.
+//!
+//! [`Loader::data_stage`]: crate::Loader::data_stage
+
+use std::{
+    fs::File,
+    io::{
+        BufReader,
+        Read,
+    },
+    path::Path,
+};
+
+use mlua::{
+    Lua,
+    Value,
+};
+use rustorio_lua_api::FromLuaValue;
+
+use crate::Error;
+
+/// Reads a `data-raw-dump.json` file.
+pub fn read_data_raw_dump<T: FromLuaValue>(path: impl AsRef<Path>) -> Result<T, Error> {
+    let path = path.as_ref();
+    log::info!("Loading data.raw dump: {}", path.display());
+    from_reader(BufReader::new(File::open(path)?))
+}
+
+pub fn from_reader<T: FromLuaValue>(reader: impl Read) -> Result<T, Error> {
+    let json: serde_json::Value = serde_json::from_reader(reader)?;
+    from_json(&json)
+}
+
+pub fn from_slice<T: FromLuaValue>(data: &[u8]) -> Result<T, Error> {
+    let json: serde_json::Value = serde_json::from_slice(data)?;
+    from_json(&json)
+}
+
+/// Converts the JSON representation of `data.raw` as the game dumps it.
+pub fn from_json<T: FromLuaValue>(json: &serde_json::Value) -> Result<T, Error> {
+    let lua = Lua::new();
+    let value = json_to_lua(&lua, json)?;
+    Ok(T::from_lua_value(value)?)
+}
+
+fn json_to_lua<'lua>(lua: &'lua Lua, json: &serde_json::Value) -> Result<Value<'lua>, Error> {
+    let value = match json {
+        serde_json::Value::Null => Value::Nil,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => {
+            if let Some(n) = n.as_i64() {
+                Value::Integer(n)
+            }
+            else {
+                Value::Number(n.as_f64().unwrap_or_default())
+            }
+        }
+        serde_json::Value::String(s) => Value::String(lua.create_string(s)?),
+        serde_json::Value::Array(items) => {
+            let table = lua.create_table_with_capacity(items.len(), 0)?;
+            for item in items {
+                table.raw_push(json_to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+        serde_json::Value::Object(fields) => {
+            let table = lua.create_table_with_capacity(0, fields.len())?;
+            for (key, value) in fields {
+                let value = json_to_lua(lua, value)?;
+                // Tables that aren't arrays are written as objects, which turns
+                // their integer keys into strings.
+                if let Some(index) = integer_key(key) {
+                    table.raw_set(index, value)?;
+                }
+                else {
+                    table.raw_set(key.as_str(), value)?;
+                }
+            }
+            Value::Table(table)
+        }
+    };
+
+    Ok(value)
+}
+
+fn integer_key(key: &str) -> Option<i64> {
+    let index: i64 = key.parse().ok()?;
+    (index.to_string() == key).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use mlua::Table;
+    use rustorio_lua_api::FromLuaTable;
+
+    use super::*;
+
+    struct Ingredients(Vec<(String, u32)>);
+
+    impl FromLuaTable for Ingredients {
+        fn from_lua_table(table: Table) -> Result<Self, rustorio_lua_api::Error> {
+            let recipe: Table = table.get::<_, Table>("recipe")?.get("iron-gear-wheel")?;
+            let mut ingredients = vec![];
+            for ingredient in recipe.get::<_, Table>("ingredients")?.sequence_values() {
+                let ingredient: Table = ingredient?;
+                ingredients.push((ingredient.get(1)?, ingredient.get(2)?));
+            }
+            Ok(Self(ingredients))
+        }
+    }
+
+    #[test]
+    fn it_converts_arrays_and_integer_keys() {
+        let Ingredients(ingredients) = from_slice(
+            br#"{
+                "recipe": {
+                    "iron-gear-wheel": {
+                        "type": "recipe",
+                        "name": "iron-gear-wheel",
+                        "ingredients": [["iron-plate", 2], {"1": "copper-plate", "2": 1}]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ingredients,
+            [("iron-plate".to_owned(), 2), ("copper-plate".to_owned(), 1)]
+        );
+    }
+}
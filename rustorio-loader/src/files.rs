@@ -1,4 +1,8 @@
 use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
     fs::File,
     io::{
         BufReader,
@@ -13,7 +17,9 @@ use std::{
 };
 
 use mlua::{
+    Function,
     Lua,
+    Table,
     Value,
 };
 use parking_lot::Mutex;
@@ -24,7 +30,10 @@ use super::{
     Mod,
     Mods,
 };
-use crate::utils::HasNextExt;
+use crate::{
+    lua::LOADED_MODULES,
+    utils::HasNextExt,
+};
 
 #[derive(Debug)]
 pub enum ModFiles {
@@ -35,6 +44,11 @@ pub enum ModFiles {
         path: PathBuf,
         zip: Mutex<ZipArchive<BufReader<File>>>,
     },
+    /// Files that only exist in memory, e.g. for tests. Paths are relative to
+    /// the mod's root directory.
+    Memory {
+        files: BTreeMap<PathBuf, Vec<u8>>,
+    },
 }
 
 impl ModFiles {
@@ -53,6 +67,21 @@ impl ModFiles {
         }
     }
 
+    /// Creates mod files from in-memory contents, keyed by their path relative
+    /// to the mod's root directory.
+    pub fn in_memory<P, D>(files: impl IntoIterator<Item = (P, D)>) -> Self
+    where
+        P: AsRef<Path>,
+        D: Into<Vec<u8>>,
+    {
+        Self::Memory {
+            files: files
+                .into_iter()
+                .map(|(path, data)| (normalize_path(path.as_ref()), data.into()))
+                .collect(),
+        }
+    }
+
     pub fn exists(&self, path: impl AsRef<Path>) -> bool {
         let file_path = path.as_ref();
 
@@ -73,6 +102,11 @@ impl ModFiles {
 
                 false
             }
+            ModFiles::Memory { files } => {
+                let file_path = normalize_path(file_path);
+                // Directories only exist implicitly, as prefixes of file paths.
+                files.keys().any(|path| path.starts_with(&file_path))
+            }
         }
     }
 
@@ -95,37 +129,71 @@ impl ModFiles {
 
                 Ok(buf)
             }
+            ModFiles::Memory { files } => {
+                files
+                    .get(&normalize_path(file_path))
+                    .cloned()
+                    .ok_or_else(|| Error::FileNotFound(file_path.to_owned()))
+            }
         }
     }
 
+    /// Lists the immediate children of a directory, files and directories,
+    /// sorted. The paths are relative to the mod's root directory, like the
+    /// paths that [`ModFiles::read`] takes, e.g. `locale/de` for
+    /// `list_dir("locale")`.
     pub fn list_dir(&self, path: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
-        let mut files = vec![];
+        let prefix = normalize_path(path.as_ref());
+        let mut files = BTreeSet::new();
 
         match self {
-            ModFiles::Direcory { path } => {
-                for dir_ent in path.read_dir()? {
+            ModFiles::Direcory { path: root } => {
+                for dir_ent in root.join(&prefix).read_dir()? {
                     let dir_ent = dir_ent?;
-                    files.push(dir_ent.path());
+                    files.insert(prefix.join(dir_ent.file_name()));
                 }
             }
             ModFiles::Zip { zip, .. } => {
-                let prefix = path.as_ref().to_string_lossy();
                 let zip = zip.lock();
-                for file_name in zip.file_names() {
-                    if file_name.starts_with(&*prefix) {
-                        files.push(file_name.into());
-                    }
-                }
+                files.extend(
+                    zip.file_names()
+                        .filter_map(|file_name| child_path(&prefix, Path::new(file_name))),
+                );
+            }
+            ModFiles::Memory { files: memory } => {
+                files.extend(
+                    memory
+                        .keys()
+                        .filter_map(|file_path| child_path(&prefix, file_path)),
+                );
             }
         }
 
-        Ok(files)
+        Ok(files.into_iter().collect())
     }
 }
 
+/// The immediate child of the directory `prefix` that contains `path`, if
+/// any. Both are compared by their components, so that `locale/de` doesn't
+/// contain `locale/debug`.
+fn child_path(prefix: &Path, path: &Path) -> Option<PathBuf> {
+    let rest = normalize_path(path);
+    let rest = rest.strip_prefix(prefix).ok()?;
+    let child = rest.components().next()?;
+    Some(prefix.join(child))
+}
+
+/// Strips `.` and leading `/` from a path, so that it can be used as key for
+/// [`ModFiles::Memory`].
+fn normalize_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
 #[derive(Debug)]
 struct ScopesData {
-    core_path: PathBuf,
+    core: ModFiles,
     import_paths: Vec<PathBuf>,
 }
 
@@ -136,16 +204,20 @@ pub struct Scopes {
 }
 
 impl Scopes {
-    pub fn new(core_path: impl AsRef<Path>, mods: Arc<Mods>) -> Self {
+    pub(crate) fn new(core: ModFiles, mods: Arc<Mods>) -> Self {
         Self {
             data: Arc::new(ScopesData {
-                core_path: core_path.as_ref().to_owned(),
+                core,
                 import_paths: vec![".".into(), "__core__/lualib/".into()],
             }),
             mods,
         }
     }
 
+    pub fn core_files(&self) -> &ModFiles {
+        &self.data.core
+    }
+
     pub fn unscoped(&self) -> Scope {
         Scope {
             scopes: self.clone(),
@@ -204,10 +276,7 @@ impl Scope {
         };
 
         let exists = match local {
-            Local::Core => {
-                let path = self.scopes.data.core_path.join(path.path);
-                path.exists()
-            }
+            Local::Core => self.scopes.data.core.exists(path.path),
             Local::Mod(fmod) => fmod.files.exists(path.path),
         };
 
@@ -224,39 +293,119 @@ impl Scope {
         };
 
         let data = match local {
-            Local::Core => {
-                let path = self.scopes.data.core_path.join(scoped_path.path);
-                std::fs::read(path)?
-            }
+            Local::Core => self.scopes.data.core.read(scoped_path.path)?,
             Local::Mod(fmod) => fmod.files.read(scoped_path.path)?,
         };
 
         Ok(data)
     }
 
-    pub fn import<'lua>(&self, lua: &'lua Lua, name: &str) -> Result<Value<'lua>, Error> {
+    /// Finds a module like the game's `require`. Module names can use `.` or
+    /// `/` as separator and may end with `.lua`. Each file is only run once
+    /// per Lua state, so e.g. `require("util")` and
+    /// `require("__core__/lualib/util")` return the same module.
+    ///
+    /// If the module wasn't loaded yet, its chunk is returned, which the caller
+    /// has to run and then store the result with [`Scope::set_loaded`].
+    pub fn find_module<'lua>(&self, lua: &'lua Lua, name: &str) -> Result<Module<'lua>, Error> {
+        let name = name.strip_suffix(".lua").unwrap_or(name);
+
         let mut path = PathBuf::new();
-        for (part, has_next) in name.split('.').has_next() {
-            if has_next {
-                path.push(part);
-            }
-            else {
-                path.push(format!("{}.lua", part));
+        if name.contains('/') {
+            path.push(format!("{}.lua", name));
+        }
+        else {
+            for (part, has_next) in name.split('.').has_next() {
+                if has_next {
+                    path.push(part);
+                }
+                else {
+                    path.push(format!("{}.lua", part));
+                }
             }
         }
 
         for import_path in &self.scopes.data.import_paths {
             let path = import_path.join(&path);
             if self.exists(&path)? {
+                let chunk_name = self.chunk_name(&path)?;
+
+                let loaded: Table = lua.named_registry_value(LOADED_MODULES)?;
+                let module: Value = loaded.get(chunk_name.as_str())?;
+                if module != Value::Nil {
+                    return Ok(Module::Loaded(module));
+                }
+
                 let source = self.read(&path)?;
-                let module: Value = lua.load(&source).set_name(path.to_string_lossy()).eval()?;
-                return Ok(module);
+                let chunk = lua
+                    .load(&source)
+                    .set_name(format!("@{}", chunk_name))
+                    .into_function()?;
+
+                return Ok(Module::Chunk { chunk_name, chunk });
             }
         }
 
         Err(Error::FileNotFound(path))
     }
 
+    /// Stores the result of running a module's chunk, so that it's not run
+    /// again.
+    pub fn set_loaded<'lua>(
+        lua: &'lua Lua,
+        chunk_name: &str,
+        module: Value<'lua>,
+    ) -> Result<Value<'lua>, Error> {
+        let module = if module == Value::Nil {
+            Value::Boolean(true)
+        }
+        else {
+            module
+        };
+        let loaded: Table = lua.named_registry_value(LOADED_MODULES)?;
+        loaded.set(chunk_name, module.clone())?;
+        Ok(module)
+    }
+
+    /// Loads a module like the game's `require`, see [`Scope::find_module`].
+    pub fn import<'lua>(&self, lua: &'lua Lua, name: &str) -> Result<Value<'lua>, Error> {
+        match self.find_module(lua, name)? {
+            Module::Loaded(module) => Ok(module),
+            Module::Chunk { chunk_name, chunk } => {
+                let module = chunk.call(())?;
+                Self::set_loaded(lua, &chunk_name, module)
+            }
+        }
+    }
+
+    /// Returns the path of a file as the game displays it, e.g.
+    /// `__base__/prototypes/item.lua`.
+    pub fn chunk_name(&self, path: impl AsRef<Path>) -> Result<String, Error> {
+        let path = path.as_ref();
+        let scoped_path = ScopedPath::new(path)?;
+
+        let scope = match (&scoped_path.scope, &self.local) {
+            (Some(ScopeId::Core), _) | (None, Some(Local::Core)) => "core",
+            (Some(ScopeId::Mod(name)), _) => name,
+            (None, Some(Local::Mod(fmod))) => fmod.name(),
+            (None, None) => return Ok(path.display().to_string()),
+        };
+
+        let file = scoped_path
+            .path
+            .components()
+            .filter_map(|component| {
+                match component {
+                    Component::Normal(s) => Some(s.to_string_lossy()),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Ok(format!("__{}__/{}", scope, file))
+    }
+
     pub fn scope_id(&self) -> Option<ScopeId> {
         let scope_id = match self.local.as_ref()? {
             Local::Core => ScopeId::Core,
@@ -266,6 +415,16 @@ impl Scope {
     }
 }
 
+/// A module found by [`Scope::find_module`].
+#[derive(Debug)]
+pub enum Module<'lua> {
+    Loaded(Value<'lua>),
+    Chunk {
+        chunk_name: String,
+        chunk: Function<'lua>,
+    },
+}
+
 #[derive(Debug)]
 pub enum ScopeId<'a> {
     Core,
@@ -332,3 +491,49 @@ impl PathError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_lists_immediate_children_relative_to_the_mod() {
+        let contents = [
+            ("locale/de/a.cfg", "a"),
+            ("locale/de/b.cfg", "b"),
+            ("locale/debug/c.cfg", "c"),
+            ("locale/en/a.cfg", "a"),
+        ];
+
+        let path = std::env::temp_dir().join(format!("rustorio-files-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        for (file, data) in contents {
+            let file = path.join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, data).unwrap();
+        }
+
+        for files in [
+            ModFiles::in_memory(contents),
+            ModFiles::open(&path).unwrap(),
+        ] {
+            assert_eq!(
+                files.list_dir("locale").unwrap(),
+                [
+                    Path::new("locale/de"),
+                    Path::new("locale/debug"),
+                    Path::new("locale/en")
+                ]
+            );
+
+            let children = files.list_dir("./locale/de").unwrap();
+            assert_eq!(
+                children,
+                [Path::new("locale/de/a.cfg"), Path::new("locale/de/b.cfg")]
+            );
+            assert_eq!(files.read(&children[1]).unwrap(), b"b");
+        }
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
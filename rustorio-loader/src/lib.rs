@@ -1,23 +1,24 @@
+pub mod cache;
+pub mod diagnostics;
+pub mod discovery;
+pub mod dump;
 pub mod files;
 pub mod lua;
+pub mod migration;
+pub mod profile;
 pub mod proptree;
+pub mod resolver;
+pub mod save;
+pub mod settings;
+pub mod trace;
 mod utils;
 
 use std::{
-    cmp::Ordering,
-    collections::{
-        HashMap,
-        HashSet,
-    },
-    fs::File,
+    collections::HashMap,
     hash::{
         Hash,
         Hasher,
     },
-    io::{
-        BufReader,
-        Read,
-    },
     path::{
         Path,
         PathBuf,
@@ -26,28 +27,52 @@ use std::{
     sync::Arc,
 };
 
-use byteorder::{
-    LittleEndian,
-    ReadBytesExt,
-};
 use lazy_static::lazy_static;
 use mlua::{
     Table,
     Value,
 };
 use regex::Regex;
-use rustorio_lua_api::FromLuaValue;
+use rustorio_locale::{
+    Locale,
+    ParseError as LocaleParseError,
+};
+use rustorio_lua_api::{
+    FromLuaTable,
+    FromLuaValue,
+};
 use serde::Deserialize;
 use thiserror::Error;
 
 use crate::{
+    diagnostics::ModError,
     files::{
         ModFiles,
         PathError,
         Scopes,
     },
     lua::FactorioLua,
-    proptree::Value as PropertyTree,
+    migration::Migrations,
+    profile::{
+        Profile,
+        Profiler,
+    },
+    settings::{
+        SettingPrototype,
+        SettingType,
+        Settings,
+        SETTING_TYPES,
+    },
+    trace::{
+        DataStage,
+        Origin,
+        Trace,
+        Tracer,
+    },
+};
+pub use crate::{
+    resolver::ModList,
+    settings::ModSettings,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -70,9 +95,6 @@ pub enum Error {
     #[error("{0}")]
     Dependency(#[from] DependencyError),
 
-    #[error("Duplicate mod: {0:?} and {1:?}")]
-    DuplicateMod(String, String),
-
     #[error("Error while parsing property tree: {0}")]
     PropertyTree(#[from] crate::proptree::Error),
 
@@ -87,8 +109,32 @@ pub enum Error {
 
     #[error("lua-api error")]
     LuaApi(#[from] rustorio_lua_api::Error),
+
+    #[error("Invalid mod settings: {0}")]
+    InvalidModSettings(String),
+
+    #[error("{0}")]
+    Mod(Box<ModError>),
+
+    #[error("Invalid save: {0}")]
+    InvalidSave(String),
+
+    #[error("Missing mods: {}", .0.join(", "))]
+    MissingMods(Vec<String>),
+
+    #[error("Mod not found: {0}")]
+    ModNotFound(String),
+
+    #[error("No Factorio installation found. Please set the data directory.")]
+    InstallationNotFound,
+
+    #[error("Locale error: {0}")]
+    Locale(#[from] LocaleParseError),
 }
 
+/// The language that is used for keys that aren't translated.
+pub const FALLBACK_LANGUAGE: &str = "en";
+
 lazy_static! {
     static ref REGEX: Regex = Regex::new(r"(\d+)\.(\d+)(\.(\d+))?").unwrap();
 }
@@ -171,10 +217,15 @@ pub struct InfoJson {
 
     pub factorio_version: Option<String>,
 
-    #[serde(default)]
+    #[serde(default = "default_dependencies")]
     pub dependencies: Vec<String>,
 }
 
+/// Mods without a `dependencies` field implicitly depend on `base`.
+fn default_dependencies() -> Vec<String> {
+    vec!["base".to_owned()]
+}
+
 #[derive(Debug, Error)]
 #[error("Failed to parse dependency: {0}")]
 pub struct DependencyParseError(String);
@@ -185,6 +236,19 @@ pub enum DependencyPrefix {
     Incompatible,
     Optional,
     HiddenOptional,
+    LoadOrderNeutral,
+}
+
+impl DependencyPrefix {
+    /// Whether the dependency must be present.
+    pub fn is_required(&self) -> bool {
+        matches!(self, Self::Hard | Self::LoadOrderNeutral)
+    }
+
+    /// Whether the dependency has to be loaded before the dependent mod.
+    pub fn affects_load_order(&self) -> bool {
+        matches!(self, Self::Hard | Self::Optional | Self::HiddenOptional)
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -196,12 +260,31 @@ pub enum DependencyOperator {
     Greater,
 }
 
+impl std::fmt::Display for DependencyOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            DependencyOperator::Less => "<",
+            DependencyOperator::LessEqual => "<=",
+            DependencyOperator::Equal => "=",
+            DependencyOperator::GreaterEqual => ">=",
+            DependencyOperator::Greater => ">",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+lazy_static! {
+    static ref DEPENDENCY_REGEX: Regex = Regex::new(
+        r"^\s*(?:(!|\?|\(\?\)|~)\s*)?([^<>=]*[^<>=\s])\s*(?:(<=|>=|<|>|=)\s*(\S+))?\s*$"
+    )
+    .unwrap();
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Dependency {
     prefix: DependencyPrefix,
     mod_name: String,
-    operator: DependencyOperator,
-    version: Version,
+    requirement: Option<(DependencyOperator, Version)>,
 }
 
 impl FromStr for Dependency {
@@ -210,172 +293,139 @@ impl FromStr for Dependency {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let err = || DependencyParseError(s.to_owned());
 
-        let parts = s.split_whitespace().collect::<Vec<&str>>();
+        let captures = DEPENDENCY_REGEX.captures(s).ok_or_else(err)?;
 
-        if parts.len() < 3 || parts.len() > 4 {
-            return Err(err());
-        }
-
-        let prefix = match parts[0] {
-            "!" => DependencyPrefix::Incompatible,
-            "?" => DependencyPrefix::Optional,
-            "(?)" => DependencyPrefix::HiddenOptional,
-            _ => DependencyPrefix::Hard,
+        let prefix = match captures.get(1).map(|m| m.as_str()) {
+            None => DependencyPrefix::Hard,
+            Some("!") => DependencyPrefix::Incompatible,
+            Some("?") => DependencyPrefix::Optional,
+            Some("(?)") => DependencyPrefix::HiddenOptional,
+            Some("~") => DependencyPrefix::LoadOrderNeutral,
+            Some(_) => return Err(err()),
         };
 
-        let i = if prefix == DependencyPrefix::Hard {
-            0
+        let mod_name = captures[2].to_owned();
+
+        let requirement = if let Some(operator) = captures.get(3) {
+            let operator = match operator.as_str() {
+                "<" => DependencyOperator::Less,
+                "<=" => DependencyOperator::LessEqual,
+                "=" => DependencyOperator::Equal,
+                ">=" => DependencyOperator::GreaterEqual,
+                ">" => DependencyOperator::Greater,
+                _ => return Err(err()),
+            };
+            let version = captures[4].parse().map_err(|_| err())?;
+            Some((operator, version))
         }
         else {
-            1
+            None
         };
 
-        let mod_name = parts[i].to_owned();
-
-        let operator = match parts[i + 1] {
-            "<" => DependencyOperator::Less,
-            "<=" => DependencyOperator::LessEqual,
-            "=" => DependencyOperator::Equal,
-            ">=" => DependencyOperator::GreaterEqual,
-            ">" => DependencyOperator::Greater,
-            _ => return Err(err()),
-        };
-
-        let version = parts[i + 2].parse().map_err(|_| err())?;
-
         Ok(Self {
             prefix,
             mod_name,
-            operator,
-            version,
+            requirement,
         })
     }
 }
 
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.prefix {
+            DependencyPrefix::Hard => {}
+            DependencyPrefix::Incompatible => write!(f, "! ")?,
+            DependencyPrefix::Optional => write!(f, "? ")?,
+            DependencyPrefix::HiddenOptional => write!(f, "(?) ")?,
+            DependencyPrefix::LoadOrderNeutral => write!(f, "~ ")?,
+        }
+        write!(f, "{}", self.mod_name)?;
+        if let Some((operator, version)) = &self.requirement {
+            write!(f, " {} {}", operator, version)?;
+        }
+        Ok(())
+    }
+}
+
 impl Dependency {
+    pub fn prefix(&self) -> DependencyPrefix {
+        self.prefix
+    }
+
+    pub fn mod_name(&self) -> &str {
+        &self.mod_name
+    }
+
+    pub fn requirement(&self) -> Option<(DependencyOperator, Version)> {
+        self.requirement
+    }
+
     fn version_matches(&self, version: &Version) -> bool {
         log::debug!("version_matches: {:?}, version={}", self, version);
 
-        match self.operator {
-            DependencyOperator::Less => *version < self.version,
-            DependencyOperator::LessEqual => *version <= self.version,
-            DependencyOperator::Equal => *version == self.version,
-            DependencyOperator::GreaterEqual => *version >= self.version,
-            DependencyOperator::Greater => *version > self.version,
+        let Some((operator, required)) = &self.requirement
+        else {
+            return true;
+        };
+
+        match operator {
+            DependencyOperator::Less => version < required,
+            DependencyOperator::LessEqual => version <= required,
+            DependencyOperator::Equal => version == required,
+            DependencyOperator::GreaterEqual => version >= required,
+            DependencyOperator::Greater => version > required,
+        }
+    }
+
+    /// Whether this dependency allows the given version of the mod it refers
+    /// to to be loaded alongside the dependent mod.
+    pub fn allows(&self, version: &Version) -> bool {
+        if self.prefix == DependencyPrefix::Incompatible {
+            !self.version_matches(version)
+        }
+        else {
+            self.version_matches(version)
         }
     }
 
     pub fn check(&self, fmod: Option<&Mod>) -> Result<(), DependencyError> {
-        match (self.prefix, fmod) {
-            (DependencyPrefix::Hard, None) => {
-                return Err(DependencyError::Missing(self.clone()));
-            }
-            (DependencyPrefix::Hard, Some(fmod)) => {
-                if !self.version_matches(&fmod.version) {
-                    return Err(DependencyError::Incompatible(
-                        fmod.name().to_owned(),
-                        self.clone(),
-                    ));
-                }
-            }
-            (DependencyPrefix::Incompatible, Some(fmod)) => {
-                if self.version_matches(&fmod.version) {
-                    return Err(DependencyError::Incompatible(
-                        fmod.name().to_owned(),
-                        self.clone(),
-                    ));
-                }
+        match fmod {
+            None if self.prefix.is_required() => Err(DependencyError::Missing(self.clone())),
+            Some(fmod) if !self.allows(&fmod.version) => {
+                Err(DependencyError::Incompatible(
+                    fmod.name().to_owned(),
+                    self.clone(),
+                ))
             }
-            _ => {}
+            _ => Ok(()),
         }
-
-        Ok(())
     }
 }
 
 #[derive(Debug, Error)]
 pub enum DependencyError {
-    #[error("Dependency cycle detected")]
+    #[error("Dependency cycle detected: {}", format_cycle(.0))]
     Cycle(Vec<(String, Dependency)>),
 
-    #[error("Missing dependency: {0:?}")]
+    #[error("Missing dependency: {0}")]
     Missing(Dependency),
 
-    #[error("Dependency {1:?} incompatible with mod: {0:?}")]
+    #[error("Dependency `{1}` incompatible with mod: {0}")]
     Incompatible(String, Dependency),
 
-    #[error("Two conflicting dependencies: {0:?} and {1:?}")]
+    #[error("Two conflicting dependencies: `{0}` and `{1}`")]
     Conflict(Dependency, Dependency),
-}
-
-struct DependencyChecker;
 
-impl DependencyChecker {
-    pub fn sort_mods(mods: &mut Mods) {
-        mods.mods.sort_by(|a, b| {
-            if a.depends_on(b).is_some() {
-                Ordering::Greater
-            }
-            else if b.depends_on(a).is_some() {
-                Ordering::Less
-            }
-            else {
-                Ordering::Equal
-            }
-        });
-    }
-
-    pub fn check(mods: &Mods) -> Result<(), DependencyError> {
-        let mut checked = HashSet::new();
-        let mut chain = vec![];
-
-        for fmod in mods.iter() {
-            if !checked.contains(fmod.name()) {
-                Self::check_mod(fmod.name(), mods, &mut chain, &mut checked)?;
-            }
-        }
-
-        Ok(())
-    }
-
-    fn check_mod<'a>(
-        mod_name: &'a str,
-        mods: &'a Mods,
-        chain: &mut Vec<(&'a str, &'a Dependency)>,
-        checked: &mut HashSet<&'a str>,
-    ) -> Result<(), DependencyError> {
-        if let Some(i) = chain
-            .iter()
-            .position(|(other_mod, _)| mod_name == *other_mod)
-        {
-            let cycle = chain[i..]
-                .iter()
-                .map(|(mod_name, dep)| ((*mod_name).to_owned(), (*dep).clone()))
-                .collect();
-            return Err(DependencyError::Cycle(cycle));
-        }
-
-        for dep in mods.get(mod_name).as_ref().unwrap().dependencies.values() {
-            let dep_mod = mods.get(&dep.mod_name).map(|m| m.as_ref());
-
-            dep.check(dep_mod)?;
-
-            if let Some(dep_mod) = dep_mod {
-                chain.push((mod_name, dep));
-
-                Self::check_mod(dep_mod.name(), mods, chain, checked)?;
-
-                {
-                    let (other_mod_name, other_dep) = chain.pop().unwrap();
-                    assert_eq!((mod_name, dep), (other_mod_name, other_dep));
-                }
-            }
-        }
-
-        checked.insert(mod_name);
+    #[error("{0}")]
+    Unsatisfiable(resolver::Explanation),
+}
 
-        Ok(())
-    }
+fn format_cycle(cycle: &[(String, Dependency)]) -> String {
+    cycle
+        .iter()
+        .map(|(mod_name, dependency)| format!("{} -> `{}`", mod_name, dependency))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(Debug)]
@@ -395,8 +445,10 @@ impl Mod {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         log::info!("Loading mod: {}", path.as_ref().display());
 
-        let path = path.as_ref();
-        let files = ModFiles::open(path)?;
+        Self::from_files(ModFiles::open(path)?)
+    }
+
+    pub fn from_files(files: ModFiles) -> Result<Self, Error> {
         let info: InfoJson = serde_json::from_slice(&files.read("info.json")?)?;
 
         let version = info.version.parse()?;
@@ -410,10 +462,13 @@ impl Mod {
         let mut dependencies = HashMap::new();
         for s in &info.dependencies {
             let dep: Dependency = s.parse()?;
+            if dep.mod_name == info.name {
+                continue;
+            }
             if let Some(dep2) = dependencies.insert(dep.mod_name.clone(), dep.clone()) {
                 log::error!("Conflicting dependencies in mod {}:", info.name);
-                log::error!(" 1. dependency: {:?}", dep2);
-                log::error!(" 2. dependency: {:?}", dep);
+                log::error!(" 1. dependency: {}", dep2);
+                log::error!(" 2. dependency: {}", dep);
                 return Err(DependencyError::Conflict(dep2.clone(), dep.clone()).into());
             }
         }
@@ -431,10 +486,18 @@ impl Mod {
         &self.info
     }
 
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
     pub fn factorio_version(&self) -> Version {
         self.factorio_version
     }
 
+    pub fn dependencies(&self) -> impl Iterator<Item = &Dependency> {
+        self.dependencies.values()
+    }
+
     pub fn name(&self) -> &str {
         &self.info.name
     }
@@ -458,42 +521,6 @@ impl Hash for Mod {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct ModSettings {
-    pub version: Version,
-    pub dev_version: u16,
-    pub settings: PropertyTree,
-}
-
-impl ModSettings {
-    pub fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
-        let version = Version::new(
-            reader.read_u16::<LittleEndian>()?.into(),
-            reader.read_u16::<LittleEndian>()?.into(),
-            reader.read_u16::<LittleEndian>()?.into(),
-        );
-
-        let dev_version = reader.read_u16::<LittleEndian>()?.into();
-
-        let _ = reader.read_u8()?;
-
-        let mut buf = vec![];
-        reader.read_to_end(&mut buf)?;
-
-        let settings = crate::proptree::from_slice(&buf)?;
-
-        Ok(ModSettings {
-            version,
-            dev_version,
-            settings,
-        })
-    }
-
-    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        Self::read(BufReader::new(File::open(path)?))
-    }
-}
-
 #[derive(Debug, Default)]
 struct Mods {
     mods: Vec<Arc<Mod>>,
@@ -518,16 +545,26 @@ impl Mods {
 }
 
 pub struct Builder {
-    core_path: PathBuf,
-    mods: Mods,
+    core: ModFiles,
+    mods: Vec<Mod>,
+    mod_list: Option<ModList>,
     settings: Option<ModSettings>,
 }
 
 impl Builder {
     pub fn new(core: impl AsRef<Path>) -> Self {
+        Self::with_core_files(ModFiles::Direcory {
+            path: core.as_ref().to_owned(),
+        })
+    }
+
+    /// Creates a builder with the given files for the `core` mod, e.g. files in
+    /// memory created with [`ModFiles::in_memory`].
+    pub fn with_core_files(core: ModFiles) -> Self {
         Self {
-            core_path: core.as_ref().to_owned(),
-            mods: Mods::default(),
+            core,
+            mods: vec![],
+            mod_list: None,
             settings: None,
         }
     }
@@ -539,16 +576,34 @@ impl Builder {
         Ok(builder)
     }
 
+    /// Adds a mod. Several versions of the same mod can be added. The newest
+    /// one that satisfies all dependencies will be loaded.
     pub fn add_mod(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
         let fmod = Mod::open(path)?;
-        self.mods.insert(fmod);
+        self.mods.push(fmod);
+        Ok(())
+    }
+
+    /// Adds a mod from its files, which don't need to exist on disk. The mod's
+    /// name and version are read from its `info.json`.
+    pub fn add_mod_files(&mut self, files: ModFiles) -> Result<(), Error> {
+        let fmod = Mod::from_files(files)?;
+        self.mods.push(fmod);
         Ok(())
     }
 
     pub fn add_mod_dir<P: AsRef<Path>>(&mut self, mod_dir: P) -> Result<(), Error> {
-        self.settings = Some(ModSettings::read_from_file(
-            mod_dir.as_ref().join("mod-settings.dat"),
-        )?);
+        let mod_list_path = mod_dir.as_ref().join("mod-list.json");
+        if mod_list_path.exists() {
+            self.mod_list = Some(ModList::read_from_file(mod_list_path)?);
+        }
+
+        // The game only creates this file once the settings are changed for the first
+        // time.
+        let settings_path = mod_dir.as_ref().join("mod-settings.dat");
+        if settings_path.exists() {
+            self.settings = Some(ModSettings::read_from_file(settings_path)?);
+        }
 
         for r in mod_dir.as_ref().read_dir()? {
             let entry = r?;
@@ -562,12 +617,23 @@ impl Builder {
         Ok(())
     }
 
-    pub fn finish(mut self) -> Result<Loader, Error> {
-        DependencyChecker::check(&self.mods)?;
-        DependencyChecker::sort_mods(&mut self.mods);
+    /// Sets which mods are enabled. Mods that are not in the list are enabled.
+    pub fn set_mod_list(&mut self, mod_list: ModList) {
+        self.mod_list = Some(mod_list);
+    }
+
+    pub fn set_mod_settings(&mut self, settings: ModSettings) {
+        self.settings = Some(settings);
+    }
 
-        let mods = Arc::new(self.mods);
-        let scopes = Scopes::new(self.core_path, mods.clone());
+    pub fn finish(self) -> Result<Loader, Error> {
+        let mut mods = Mods::default();
+        for fmod in resolver::resolve(self.mods, self.mod_list.as_ref())? {
+            mods.insert(fmod);
+        }
+
+        let mods = Arc::new(mods);
+        let scopes = Scopes::new(self.core, mods.clone());
 
         Ok(Loader {
             settings: self.settings,
@@ -598,69 +664,633 @@ impl Loader {
         Ok(builder.finish()?)
     }
 
-    fn run_with_all(&self, lua: &FactorioLua, file_name: impl AsRef<Path>) -> Result<(), Error> {
-        let file_name = file_name.as_ref();
+    fn set_mods(&self, lua: &FactorioLua) -> Result<(), Error> {
+        lua.set_mods(
+            self.mods
+                .iter()
+                .map(|fmod| (fmod.name(), fmod.version().to_string())),
+        )
+    }
+
+    /// Runs a file of a mod, if it exists. If `errors` is given, errors raised
+    /// by the mod are added to it instead of being returned, and mods that
+    /// already failed are skipped. Returns whether the file ran successfully.
+    fn run_mod_file(
+        &self,
+        lua: &FactorioLua,
+        fmod: &Arc<Mod>,
+        file_name: &str,
+        errors: Option<&mut Vec<ModError>>,
+    ) -> Result<bool, Error> {
+        if let Some(errors) = &errors {
+            if errors.iter().any(|error| error.mod_name == fmod.name()) {
+                return Ok(false);
+            }
+        }
 
+        let scope = self.scopes.mod_scope(fmod.clone());
+        if !scope.exists(file_name)? {
+            return Ok(false);
+        }
+
+        lua.set_loader(scope.clone())?;
+        match (lua.run_mod_script(file_name, scope), errors) {
+            (Ok(()), _) => Ok(true),
+            (Err(Error::Mod(error)), Some(errors)) => {
+                log::warn!("{}", error);
+                log::warn!("Skipping remaining files of mod {}", error.mod_name);
+                errors.push(*error);
+                Ok(false)
+            }
+            (Err(error), _) => Err(error),
+        }
+    }
+
+    fn run_with_all(
+        &self,
+        lua: &FactorioLua,
+        file_name: &str,
+        mut errors: Option<&mut Vec<ModError>>,
+    ) -> Result<(), Error> {
         for fmod in self.mods.iter() {
-            let scope = self.scopes.mod_scope(fmod.clone());
-            if scope.exists(file_name)? {
-                lua.set_loader(scope.clone())?;
-                lua.run_script_from_file(file_name, scope)?;
+            self.run_mod_file(lua, fmod, file_name, errors.as_deref_mut())?;
+        }
+
+        Ok(())
+    }
+
+    fn run_data_stage(
+        &self,
+        lua: &FactorioLua,
+        stage: DataStage,
+        mut tracer: Option<&mut Tracer>,
+        mut errors: Option<&mut Vec<ModError>>,
+    ) -> Result<(), Error> {
+        let file_name = stage.file_name();
+
+        for fmod in self.mods.iter() {
+            if self.run_mod_file(lua, fmod, file_name, errors.as_deref_mut())? {
+                if let Some(tracer) = tracer.as_deref_mut() {
+                    let origin = Origin {
+                        mod_name: fmod.name().to_owned(),
+                        stage,
+                    };
+                    tracer.record(&data_raw(lua)?, origin)?;
+                }
             }
         }
 
         Ok(())
     }
 
-    fn settings_stage(&self) -> Result<(), Error> {
-        let mut lua = FactorioLua::new()?;
+    /// Runs the settings stage and determines the values of all startup
+    /// settings from the setting prototypes and the user's
+    /// `mod-settings.dat`.
+    pub fn settings_stage(&self) -> Result<Settings, Error> {
+        self.run_settings_stage(None, None)
+    }
 
-        let mods = lua.create_table()?;
-        // todo: populate table
+    fn run_settings_stage(
+        &self,
+        mut errors: Option<&mut Vec<ModError>>,
+        mut profiler: Option<&mut Profiler>,
+    ) -> Result<Settings, Error> {
+        let lua = FactorioLua::new()?;
+        if let Some(profiler) = profiler.as_deref_mut() {
+            profiler.attach(&lua);
+        }
+        self.set_mods(&lua)?;
 
-        let globals = lua.globals();
-        globals.set("mods", mods)?;
-        drop(globals);
+        let scope = self.scopes.core_scope();
+        lua.set_loader(scope.clone())?;
+        lua.run_script_from_file::<()>("lualib/dataloader.lua", scope)?;
+        lua.track_insertion_order(data_raw(&lua)?, 2)?;
 
-        self.run_with_all(&mut lua, "settings.lua")?;
-        self.run_with_all(&mut lua, "settings-updates.lua")?;
-        self.run_with_all(&mut lua, "settings-final-fixes.lua")?;
+        self.run_with_all(&lua, "settings.lua", errors.as_deref_mut())?;
+        self.run_with_all(&lua, "settings-updates.lua", errors.as_deref_mut())?;
+        self.run_with_all(&lua, "settings-final-fixes.lua", errors)?;
 
-        // TODO: load mod-settings.dat
+        if let Some(profiler) = profiler {
+            profiler.detach(&lua);
+        }
 
-        Ok(())
+        let data_raw = lua
+            .globals()
+            .get::<_, Table>("data")?
+            .get::<_, Table>("raw")?;
+
+        let mut settings = Settings::default();
+        for setting_type in SETTING_TYPES {
+            let Some(prototypes) = data_raw.get::<_, Option<Table>>(*setting_type)?
+            else {
+                continue;
+            };
+
+            for r in prototypes.pairs::<String, Table>() {
+                let (_, table) = r?;
+                let prototype = SettingPrototype::from_lua_table(table)?;
+                settings
+                    .prototypes
+                    .insert(prototype.name.clone(), prototype);
+            }
+        }
+
+        for prototype in settings.prototypes.values() {
+            if prototype.setting_type != SettingType::Startup {
+                continue;
+            }
+
+            let user_value = self
+                .settings
+                .as_ref()
+                .and_then(|mod_settings| mod_settings.get(prototype.setting_type, &prototype.name))
+                .cloned();
+
+            settings
+                .startup
+                .insert(prototype.name.clone(), prototype.resolve(user_value));
+        }
+
+        Ok(settings)
     }
 
     pub fn data_stage<T: FromLuaValue>(&self) -> Result<T, crate::Error> {
-        let mut lua = FactorioLua::new()?;
+        self.run_data_stages(None, None, None)
+    }
 
-        // Initialize the lua context.
-        let mods = lua.create_table()?; // TODO
-        let settings = lua.create_table()?; // TODO
+    /// Like [`Loader::data_stage`], but doesn't stop if a mod raises an error.
+    /// Instead the remaining settings and data stage files of that mod are
+    /// skipped, and the error is returned together with the prototypes of all
+    /// other mods. Prototypes that the failed mod already created or changed
+    /// are kept.
+    pub fn data_stage_tolerant<T: FromLuaValue>(&self) -> Result<(T, Vec<ModError>), crate::Error> {
+        let mut errors = vec![];
+        let data = self.run_data_stages(None, Some(&mut errors), None)?;
+        Ok((data, errors))
+    }
 
-        let globals = lua.globals();
-        globals.set("mods", mods)?;
-        globals.set("settings", settings)?;
-        drop(globals);
+    /// Like [`Loader::data_stage`], but also records which mod and file created
+    /// and modified each prototype. This is slower, since `data.raw` is
+    /// inspected after every file that runs.
+    pub fn data_stage_traced<T: FromLuaValue>(&self) -> Result<(T, Trace), crate::Error> {
+        let mut tracer = Tracer::default();
+        let data = self.run_data_stages(Some(&mut tracer), None, None)?;
+        Ok((data, tracer.finish()))
+    }
+
+    /// Like [`Loader::data_stage`], but also measures the time and memory
+    /// used by each stage file and module of the settings and data stages.
+    pub fn data_stage_profiled<T: FromLuaValue>(&self) -> Result<(T, Profile), crate::Error> {
+        let mut profiler = Profiler::default();
+        let data = self.run_data_stages(None, None, Some(&mut profiler))?;
+        Ok((data, profiler.finish()))
+    }
+
+    fn run_data_stages<T: FromLuaValue>(
+        &self,
+        mut tracer: Option<&mut Tracer>,
+        mut errors: Option<&mut Vec<ModError>>,
+        mut profiler: Option<&mut Profiler>,
+    ) -> Result<T, crate::Error> {
+        let settings = self.run_settings_stage(errors.as_deref_mut(), profiler.as_deref_mut())?;
+
+        let lua = FactorioLua::new()?;
+        if let Some(profiler) = profiler.as_deref_mut() {
+            profiler.attach(&lua);
+        }
+
+        // Initialize the lua context.
+        self.set_mods(&lua)?;
+        let settings = settings.to_lua_table(&lua)?;
+        lua.globals().set("settings", settings)?;
 
         let scope = self.scopes.core_scope();
         lua.set_loader(scope.clone())?;
-        lua.run_script_from_file("lualib/dataloader.lua", scope.clone())?;
-        lua.run_script_from_file("data.lua", scope)?;
+        lua.run_script_from_file::<()>("lualib/dataloader.lua", scope.clone())?;
+        // `data.raw` and the tables for each prototype type are iterated in the
+        // order in which prototypes were added.
+        lua.track_insertion_order(data_raw(&lua)?, 2)?;
+        lua.run_mod_script("data.lua", scope)?;
+
+        if let Some(tracer) = tracer.as_deref_mut() {
+            let origin = Origin {
+                mod_name: "core".to_owned(),
+                stage: DataStage::Data,
+            };
+            tracer.record(&data_raw(&lua)?, origin)?;
+        }
 
-        self.run_with_all(&mut lua, "data.lua")?;
-        self.run_with_all(&mut lua, "data-updates.lua")?;
-        self.run_with_all(&mut lua, "data-final-fixes.lua")?;
+        self.run_data_stage(
+            &lua,
+            DataStage::Data,
+            tracer.as_deref_mut(),
+            errors.as_deref_mut(),
+        )?;
+        self.run_data_stage(
+            &lua,
+            DataStage::DataUpdates,
+            tracer.as_deref_mut(),
+            errors.as_deref_mut(),
+        )?;
+        self.run_data_stage(&lua, DataStage::DataFinalFixes, tracer, errors)?;
+
+        if let Some(profiler) = profiler {
+            profiler.detach(&lua);
+        }
 
-        let data_raw = lua
-            .globals()
-            .get::<_, Table>("data")?
-            .get::<_, Value>("raw")?;
+        let data_raw = data_raw(&lua)?;
+        Ok(T::from_lua_value(Value::Table(data_raw))?)
+    }
+
+    /// Reads the JSON migrations of all mods, in load order.
+    pub fn migrations(&self) -> Result<Migrations, Error> {
+        let mut migrations = Migrations::default();
+        for fmod in self.mods.iter() {
+            migrations.add_mod(fmod)?;
+        }
+        Ok(migrations)
+    }
+
+    /// Reads the locale files `locale/<language>/*.cfg` of core and all mods in
+    /// load order, so that later mods override earlier ones. Keys that are not
+    /// translated fall back to English.
+    pub fn locale(&self, language: &str) -> Result<Locale, Error> {
+        let mut locale = self.read_locale(FALLBACK_LANGUAGE)?;
+        if language != FALLBACK_LANGUAGE {
+            locale.merge_into(self.read_locale(language)?);
+        }
+        Ok(locale)
+    }
 
-        Ok(T::from_lua_value(data_raw)?)
+    /// Reads the locale files of `core` and all mods for a language, without
+    /// falling back to English.
+    pub fn read_locale(&self, language: &str) -> Result<Locale, Error> {
+        let mut locale = Locale::default();
+        for (mod_name, files) in self.locale_sources() {
+            locale.merge_into(read_mod_locale(mod_name, files, language)?);
+        }
+        Ok(locale)
+    }
+
+    /// `core` and all mods in load order, with their files.
+    fn locale_sources(&self) -> impl Iterator<Item = (&str, &ModFiles)> {
+        std::iter::once(("core", self.scopes.core_files()))
+            .chain(self.mods.iter().map(|fmod| (fmod.name(), &fmod.files)))
+    }
+
+    /// The names of `core` and all mods that have locale files, in load order.
+    pub fn locale_mods(&self) -> Vec<&str> {
+        self.locale_sources()
+            .filter(|(_, files)| files.exists("locale"))
+            .map(|(mod_name, _)| mod_name)
+            .collect()
+    }
+
+    /// The languages that a mod, or `core`, has locale files for, sorted.
+    pub fn mod_languages(&self, mod_name: &str) -> Result<Vec<String>, Error> {
+        let files = self.locale_files(mod_name)?;
+        if !files.exists("locale") {
+            return Ok(vec![]);
+        }
+
+        let mut languages = files
+            .list_dir("locale")?
+            .into_iter()
+            .filter_map(|path| Some(path.file_name()?.to_str()?.to_owned()))
+            .collect::<Vec<_>>();
+        languages.sort();
+        Ok(languages)
+    }
+
+    /// Reads the locale files of a single mod, or `core`, for a language,
+    /// without falling back to English.
+    pub fn mod_locale(&self, mod_name: &str, language: &str) -> Result<Locale, Error> {
+        read_mod_locale(mod_name, self.locale_files(mod_name)?, language)
+    }
+
+    fn locale_files(&self, mod_name: &str) -> Result<&ModFiles, Error> {
+        if mod_name == "core" {
+            Ok(self.scopes.core_files())
+        }
+        else {
+            self.mods
+                .get(mod_name)
+                .map(|fmod| &fmod.files)
+                .ok_or_else(|| Error::ModNotFound(mod_name.to_owned()))
+        }
     }
 
     pub fn read_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
         self.scopes.unscoped().read(path)
     }
 }
+
+/// Reads the files `locale/<language>/*.cfg` of a mod, sorted by name.
+fn read_mod_locale(mod_name: &str, files: &ModFiles, language: &str) -> Result<Locale, Error> {
+    let mut locale = Locale::default();
+    let dir = Path::new("locale").join(language);
+    if !files.exists(&dir) {
+        return Ok(locale);
+    }
+
+    let mut paths = files
+        .list_dir(&dir)?
+        .into_iter()
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("cfg"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        let data = String::from_utf8(files.read(&path)?).map_err(LocaleParseError::from)?;
+        let file_locale = Locale::parse(&data).inspect_err(|_| {
+            log::error!(
+                "Failed to parse locale file of mod {}: {}",
+                mod_name,
+                path.display()
+            );
+        })?;
+        locale.merge_into(file_locale);
+    }
+
+    Ok(locale)
+}
+
+fn data_raw(lua: &FactorioLua) -> Result<Table<'_>, mlua::Error> {
+    lua.globals()
+        .get::<_, Table>("data")?
+        .get::<_, Table>("raw")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::profile::SpanKind;
+
+    /// The names of all items in `data.raw`, and their `order`.
+    struct ItemOrders(BTreeMap<String, String>);
+
+    impl FromLuaTable for ItemOrders {
+        fn from_lua_table(table: Table) -> Result<Self, rustorio_lua_api::Error> {
+            let mut orders = BTreeMap::new();
+            for r in table.get::<_, Table>("item")?.pairs::<String, Table>() {
+                let (name, item) = r?;
+                orders.insert(name, item.get("order")?);
+            }
+            Ok(Self(orders))
+        }
+    }
+
+    fn info_json(name: &str, dependencies: &[&str]) -> String {
+        format!(
+            r#"{{"name": "{}", "version": "1.0.0", "title": "", "author": "", "dependencies": {:?}}}"#,
+            name, dependencies
+        )
+    }
+
+    fn test_builder() -> Builder {
+        let mut builder = Builder::with_core_files(ModFiles::in_memory([
+            (
+                "lualib/dataloader.lua",
+                r#"
+                data = { raw = {} }
+                function data.extend(self, prototypes)
+                    for _, prototype in ipairs(prototypes) do
+                        data.raw[prototype.type] = data.raw[prototype.type] or {}
+                        data.raw[prototype.type][prototype.name] = prototype
+                    end
+                end
+                "#
+                .to_owned(),
+            ),
+            ("data.lua", "".to_owned()),
+        ]));
+
+        builder
+            .add_mod_files(ModFiles::in_memory([
+                ("info.json", info_json("base", &[])),
+                ("data.lua", r#"require("prototypes.item")"#.to_owned()),
+                (
+                    "prototypes/item.lua",
+                    r#"
+                    data:extend({{ type = "item", name = "iron-plate", order = "a" }})
+                    return "base"
+                    "#
+                    .to_owned(),
+                ),
+            ]))
+            .unwrap();
+
+        // `foo` sorts before `zzz`, but has to be loaded after it.
+        builder
+            .add_mod_files(ModFiles::in_memory([
+                ("info.json", info_json("foo", &["base", "? zzz"])),
+                (
+                    "data.lua",
+                    r#"
+                    local own = require("prototypes.item")
+                    local base = require("__base__/prototypes/item")
+                    local zzz = data.raw.item["zzz-plate"] and "zzz" or "none"
+                    data:extend({{ type = "item", name = "foo-plate", order = own .. "," .. base .. "," .. zzz }})
+                    "#
+                    .to_owned(),
+                ),
+                ("prototypes/item.lua", r#"return "foo""#.to_owned()),
+            ]))
+            .unwrap();
+
+        builder
+            .add_mod_files(ModFiles::in_memory([
+                ("info.json", info_json("zzz", &["base"])),
+                (
+                    "data.lua",
+                    r#"data:extend({{ type = "item", name = "zzz-plate", order = "z" }})"#
+                        .to_owned(),
+                ),
+                (
+                    "data-updates.lua",
+                    r#"data.raw.item["iron-plate"].order = "b""#.to_owned(),
+                ),
+            ]))
+            .unwrap();
+
+        builder
+    }
+
+    fn test_loader() -> Loader {
+        test_builder().finish().unwrap()
+    }
+
+    #[test]
+    fn it_runs_the_data_stage_with_virtual_mods() {
+        let loader = test_loader();
+
+        let ItemOrders(orders) = loader.data_stage().unwrap();
+
+        assert_eq!(
+            orders.into_iter().collect::<Vec<_>>(),
+            [
+                ("foo-plate".to_owned(), "foo,base,zzz".to_owned()),
+                ("iron-plate".to_owned(), "b".to_owned()),
+                ("zzz-plate".to_owned(), "z".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reads_virtual_files_by_scoped_path() {
+        let loader = test_loader();
+
+        assert_eq!(
+            loader.read_file("__foo__/prototypes/item.lua").unwrap(),
+            br#"return "foo""#
+        );
+        assert!(matches!(
+            loader.read_file("__foo__/missing.lua"),
+            Err(Error::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn it_skips_mods_that_fail() {
+        let mut builder = test_builder();
+        builder
+            .add_mod_files(ModFiles::in_memory([
+                ("info.json", info_json("broken", &["base"])),
+                (
+                    "data.lua",
+                    r#"data:extend({{ type = "item", name = "broken-plate", order = "x" }})"#
+                        .to_owned(),
+                ),
+                (
+                    "data-updates.lua",
+                    "require(\"prototypes.update\")".to_owned(),
+                ),
+                (
+                    "prototypes/update.lua",
+                    "local item = data.raw.item[\"broken-plate\"]\nitem.order = data.raw.item.missing.order"
+                        .to_owned(),
+                ),
+                (
+                    "data-final-fixes.lua",
+                    r#"data.raw.item["broken-plate"].order = "y""#.to_owned(),
+                ),
+            ]))
+            .unwrap();
+        let loader = builder.finish().unwrap();
+
+        let error = match loader.data_stage::<ItemOrders>() {
+            Err(Error::Mod(error)) => error,
+            _ => panic!("expected mod error"),
+        };
+        assert_eq!(error.mod_name, "broken");
+        assert_eq!(error.file, "__broken__/data-updates.lua");
+        assert_eq!(
+            error.location,
+            Some(("__broken__/prototypes/update.lua".to_owned(), 2))
+        );
+        assert!(error
+            .traceback
+            .as_deref()
+            .is_some_and(|traceback| traceback.contains("__broken__/data-updates.lua:1")));
+
+        let (ItemOrders(orders), errors) = loader.data_stage_tolerant().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(orders["broken-plate"], "x");
+        assert_eq!(orders["foo-plate"], "foo,base,zzz");
+    }
+
+    #[test]
+    fn it_profiles_files_and_modules() {
+        let loader = test_loader();
+
+        let (_, profile): (ItemOrders, _) = loader.data_stage_profiled().unwrap();
+
+        let spans = profile
+            .spans
+            .iter()
+            .map(|span| (span.name.as_str(), span.kind, span.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                ("__core__/data.lua", SpanKind::File, 0),
+                ("__base__/data.lua", SpanKind::File, 0),
+                ("__base__/prototypes/item.lua", SpanKind::Module, 1),
+                ("__zzz__/data.lua", SpanKind::File, 0),
+                ("__foo__/data.lua", SpanKind::File, 0),
+                ("__foo__/prototypes/item.lua", SpanKind::Module, 1),
+                ("__zzz__/data-updates.lua", SpanKind::File, 0),
+            ]
+        );
+        assert_eq!(profile.by_mod()["base"].files, 1);
+
+        let mut trace = vec![];
+        profile.write_chrome_trace(&mut trace).unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&trace).unwrap();
+        assert_eq!(trace["traceEvents"].as_array().unwrap().len(), 7);
+    }
+
+    #[test]
+    fn it_merges_locales_with_english_fallback() {
+        let mut builder = test_builder();
+        builder
+            .add_mod_files(ModFiles::in_memory([
+                ("info.json", info_json("translated", &["base"])),
+                (
+                    "locale/en/items.cfg",
+                    "[item-name]\niron-plate=Iron plate\nfoo-plate=Foo plate\n".to_owned(),
+                ),
+                (
+                    "locale/de/items.cfg",
+                    "[item-name]\niron-plate=Eisenplatte\n".to_owned(),
+                ),
+            ]))
+            .unwrap();
+        let loader = builder.finish().unwrap();
+
+        let text = |locale: &Locale, key: &str| {
+            match locale.get(key).unwrap().elements.as_slice() {
+                [rustorio_locale::LocalizationElement::Text(text)] => text.clone(),
+                elements => panic!("unexpected elements: {:?}", elements),
+            }
+        };
+
+        let locale = loader.locale("de").unwrap();
+        assert_eq!(text(&locale, "item-name.iron-plate"), "Eisenplatte");
+        assert_eq!(text(&locale, "item-name.foo-plate"), "Foo plate");
+
+        assert!(loader.locale_mods().contains(&"translated"));
+        assert_eq!(loader.mod_languages("translated").unwrap(), ["de", "en"]);
+        let locale = loader.mod_locale("translated", "de").unwrap();
+        assert!(locale.get("item-name.foo-plate").is_none());
+    }
+
+    #[test]
+    fn it_reads_locales_of_directory_mods() {
+        // Mod directories are often given with relative components, e.g.
+        // `mods/../my-mod`. Files are still listed relative to the mod.
+        let root =
+            std::env::temp_dir().join(format!("rustorio-locale-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("mods")).unwrap();
+        std::fs::create_dir_all(root.join("test").join("locale").join("de")).unwrap();
+        std::fs::write(
+            root.join("test")
+                .join("locale")
+                .join("de")
+                .join("items.cfg"),
+            "[item-name]\niron-plate=Eisenplatte\n",
+        )
+        .unwrap();
+
+        let files = ModFiles::open(root.join("mods").join("..").join("test")).unwrap();
+        let listed = files.list_dir("locale/de");
+        let locale = read_mod_locale("test", &files, "de");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(listed.unwrap(), [PathBuf::from("locale/de/items.cfg")]);
+        assert!(locale.unwrap().get("item-name.iron-plate").is_some());
+    }
+}
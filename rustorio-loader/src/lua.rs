@@ -8,10 +8,21 @@ use mlua::{
     FromLuaMulti,
     Lua,
     StdLib,
+    Table,
+    Value,
 };
 
 use super::{
-    files::Scope,
+    diagnostics::ModError,
+    files::{
+        Module,
+        Scope,
+        ScopeId,
+    },
+    profile::{
+        Profiler,
+        SpanKind,
+    },
     Error,
 };
 
@@ -33,17 +44,143 @@ impl FactorioLua {
         let defines = import_defines(&lua)?;
         lua.globals().set("defines", defines)?;
 
+        // Modules loaded by `require`, by their full path.
+        lua.set_named_registry_value(LOADED_MODULES, lua.create_table()?)?;
+
+        // Iterate tables in a deterministic order. This is not the game's `pairs`:
+        // only tables that are tracked with `track_insertion_order`, i.e.
+        // `data.raw` and its type tables, are iterated in insertion order. All
+        // other tables, including the fields of prototypes, are iterated by
+        // sorted keys. `getmetatable` and `setmetatable` are replaced, so that
+        // mods don't see the metatables used for tracking. See `ordered.lua`.
+        {
+            let ordered: Table = lua.load(ORDERED).set_name("=ordered").eval()?;
+            lua.globals()
+                .set("next", ordered.get::<_, Value>("next")?)?;
+            lua.globals()
+                .set("pairs", ordered.get::<_, Value>("pairs")?)?;
+            lua.globals()
+                .set("getmetatable", ordered.get::<_, Value>("getmetatable")?)?;
+            lua.globals()
+                .set("setmetatable", ordered.get::<_, Value>("setmetatable")?)?;
+            lua.set_named_registry_value(TRACK_INSERTION_ORDER, ordered.get::<_, Value>("track")?)?;
+        }
+
+        let make_require = lua
+            .load(
+                r#"
+                local error = error
+                return function(find, set_loaded)
+                    return function(name)
+                        local module, chunk, chunk_name, err = find(name)
+                        if err then
+                            error(err, 2)
+                        elseif chunk then
+                            module = set_loaded(chunk_name, chunk())
+                        end
+                        return module
+                    end
+                end
+                "#,
+            )
+            .set_name("=require")
+            .eval::<mlua::Function>()?;
+        lua.set_named_registry_value(MAKE_REQUIRE, make_require)?;
+
+        // Calls a function and returns the traceback if it raises an error.
+        let run_traced = lua
+            .load(
+                r#"
+                local xpcall, traceback = xpcall, debug.traceback
+                return function(f)
+                    local tb
+                    local ok, err = xpcall(f, function(e)
+                        tb = traceback(nil, 2)
+                        return e
+                    end)
+                    return ok, err, tb
+                end
+                "#,
+            )
+            .set_name("=run_traced")
+            .eval::<mlua::Function>()?;
+        lua.set_named_registry_value(RUN_TRACED, run_traced)?;
+
+        let serpent: Table = lua.load(SERPENT).set_name("=serpent").eval()?;
+        lua.globals().set("serpent", serpent)?;
+
+        lua.globals()
+            .set("log", lua.create_function(log_message)?)?;
+
+        // The game prints to stdout, but that is the output of our tools, so this
+        // is logged like `log`.
+        lua.globals()
+            .set("localised_print", lua.create_function(log_message)?)?;
+
+        let table_size = lua.create_function(|_, table: Table| {
+            let mut n = 0;
+            for r in table.pairs::<Value, Value>() {
+                r?;
+                n += 1;
+            }
+            Ok(n)
+        })?;
+        lua.globals().set("table_size", table_size)?;
+
         Ok(Self { lua })
     }
 
+    /// Sets the `mods` global, which maps the names of all active mods to
+    /// their versions.
+    pub fn set_mods<'a>(
+        &self,
+        mods: impl IntoIterator<Item = (&'a str, String)>,
+    ) -> Result<(), Error> {
+        let table = self.lua.create_table()?;
+        for (name, version) in mods {
+            table.set(name, version)?;
+        }
+        self.lua.globals().set("mods", table)?;
+        Ok(())
+    }
+
+    /// Makes `pairs` and `next` iterate `table` in insertion order, as well as
+    /// all tables inserted into it, up to `depth` levels deep. Other tables
+    /// are iterated by sorted keys. Tables that already have a metatable are
+    /// not tracked.
+    pub fn track_insertion_order(&self, table: Table, depth: usize) -> Result<(), Error> {
+        let track: mlua::Function = self.lua.named_registry_value(TRACK_INSERTION_ORDER)?;
+        track.call::<_, ()>((table, depth))?;
+        Ok(())
+    }
+
     pub fn set_loader(&self, scope: Scope) -> Result<(), Error> {
-        let require = self.lua.create_function(move |lua, name: String| {
+        // The module's chunk is run by the Lua side of `require`, so that errors in
+        // it are raised with their location and traceback intact.
+        let find = self.lua.create_function(move |lua, name: String| {
             log::debug!("require called: {}", name);
 
-            let module = scope.import(lua, &name).map_err(lua_error)?;
-            Ok(module)
+            match scope.find_module(lua, &name) {
+                Ok(Module::Loaded(module)) => Ok((module, Value::Nil, None, None)),
+                Ok(Module::Chunk { chunk_name, chunk }) => {
+                    Profiler::enter(lua, SpanKind::Module, &chunk_name);
+                    Ok((Value::Nil, Value::Function(chunk), Some(chunk_name), None))
+                }
+                Err(Error::Lua(mlua::Error::SyntaxError { message, .. })) => {
+                    Ok((Value::Nil, Value::Nil, None, Some(message)))
+                }
+                Err(e) => Ok((Value::Nil, Value::Nil, None, Some(e.to_string()))),
+            }
         })?;
+        let set_loaded =
+            self.lua
+                .create_function(|lua, (chunk_name, module): (String, Value)| {
+                    Profiler::exit(lua, &chunk_name);
+                    Scope::set_loaded(lua, &chunk_name, module).map_err(lua_error)
+                })?;
 
+        let make_require: mlua::Function = self.lua.named_registry_value(MAKE_REQUIRE)?;
+        let require: mlua::Function = make_require.call((find, set_loaded))?;
         self.lua.globals().set("require", require)?;
 
         Ok(())
@@ -67,8 +204,56 @@ impl FactorioLua {
     ) -> Result<R, Error> {
         let path = path.as_ref();
         log::debug!("run script: {} ({:?})", path.display(), scope.scope_id());
-        let code = scope.read(&path).map_err(lua_error)?;
-        self.run_script(path.display(), code)
+        let code = scope.read(path).map_err(lua_error)?;
+        let name = format!("@{}", scope.chunk_name(path)?);
+        self.run_script(name, code)
+    }
+
+    /// Like [`FactorioLua::run_script_from_file`], but errors raised by the
+    /// script are returned as [`Error::Mod`], with the Lua traceback.
+    pub fn run_mod_script(&self, path: impl AsRef<Path>, scope: Scope) -> Result<(), Error> {
+        let path = path.as_ref();
+        log::debug!(
+            "run mod script: {} ({:?})",
+            path.display(),
+            scope.scope_id()
+        );
+        let code = scope.read(path)?;
+        let chunk_name = scope.chunk_name(path)?;
+        let mod_name = match scope.scope_id() {
+            Some(ScopeId::Mod(name)) => name.to_owned(),
+            _ => "core".to_owned(),
+        };
+        let mod_error = |message: String, traceback: Option<String>| {
+            Error::Mod(Box::new(ModError::new(
+                mod_name.as_str(),
+                chunk_name.as_str(),
+                message,
+                traceback,
+            )))
+        };
+
+        let function = match self
+            .load(&code)
+            .set_name(format!("@{}", chunk_name))
+            .into_function()
+        {
+            Ok(function) => function,
+            Err(mlua::Error::SyntaxError { message, .. }) => return Err(mod_error(message, None)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let run_traced: mlua::Function = self.named_registry_value(RUN_TRACED)?;
+        Profiler::enter(self, SpanKind::File, &chunk_name);
+        let result = run_traced.call::<_, (bool, Value, Option<String>)>(function);
+        Profiler::exit(self, &chunk_name);
+        let (ok, error, traceback) = result?;
+        if !ok {
+            let message = message_to_string(self, error)?;
+            return Err(mod_error(message, traceback));
+        }
+
+        Ok(())
     }
 }
 
@@ -80,6 +265,66 @@ impl Deref for FactorioLua {
     }
 }
 
+const SERPENT: &str = include_str!("serpent.lua");
+
+const ORDERED: &str = include_str!("ordered.lua");
+
+const TRACK_INSERTION_ORDER: &str = "rustorio_track_insertion_order";
+
+const RUN_TRACED: &str = "rustorio_run_traced";
+
+const MAKE_REQUIRE: &str = "rustorio_make_require";
+
+pub(crate) const LOADED_MODULES: &str = "rustorio_loaded_modules";
+
+/// Logs a message from Lua with the mod that it comes from as target, e.g.
+/// `factorio::base`.
+fn log_message(lua: &Lua, message: Value) -> Result<(), mlua::Error> {
+    let (source, line) = caller(lua);
+    let message = message_to_string(lua, message)?;
+    let mod_name = mod_name_from_source(&source).unwrap_or("unknown");
+    log::info!(target: &format!("factorio::{}", mod_name), "{}:{}: {}", source, line, message);
+    Ok(())
+}
+
+/// Returns the source file and line of the Lua function that called the
+/// currently running Rust function.
+fn caller(lua: &Lua) -> (String, i32) {
+    lua.inspect_stack(1)
+        .map(|debug| {
+            let source = debug
+                .source()
+                .short_src
+                .map(|s| s.into_owned())
+                .unwrap_or_default();
+            (source, debug.curr_line())
+        })
+        .unwrap_or_default()
+}
+
+/// Extracts the mod name from a chunk name like `__base__/data.lua`.
+pub fn mod_name_from_source(source: &str) -> Option<&str> {
+    source
+        .strip_prefix("__")?
+        .split_once("__/")
+        .map(|(name, _)| name)
+}
+
+fn message_to_string(lua: &Lua, message: Value) -> Result<String, mlua::Error> {
+    match message {
+        Value::String(s) => Ok(s.to_string_lossy().into_owned()),
+        Value::Table(table) => {
+            let serpent: Table = lua.globals().get("serpent")?;
+            serpent.get::<_, mlua::Function>("line")?.call(table)
+        }
+        other => {
+            lua.globals()
+                .get::<_, mlua::Function>("tostring")?
+                .call(other)
+        }
+    }
+}
+
 pub fn import_defines(lua: &Lua) -> Result<mlua::Value, Error> {
     // run the following code in-game to export the defines
     // /c game.write_file('defines.lua', serpent.block(defines))
@@ -89,3 +334,132 @@ pub fn import_defines(lua: &Lua) -> Result<mlua::Value, Error> {
 
     Ok(defines)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_provides_factorio_globals() {
+        let lua = FactorioLua::new().unwrap();
+
+        let size: usize = lua
+            .run_script("test", "return table_size({1, 2, x = 3})")
+            .unwrap();
+        assert_eq!(size, 3);
+
+        let line: String = lua
+            .run_script(
+                "test",
+                r#"return serpent.line({1, "a", b = {c = true}, ["d-e"] = 0.5})"#,
+            )
+            .unwrap();
+        assert_eq!(line, r#"{1, "a", b = {c = true}, ["d-e"] = 0.5}"#);
+
+        let ok: bool = lua
+            .run_script(
+                "test",
+                r#"
+                local ok, t = serpent.load(serpent.dump({1, {2}, k = "v"}))
+                return ok and t[2][1] == 2 and t.k == "v"
+                "#,
+            )
+            .unwrap();
+        assert!(ok);
+    }
+
+    #[test]
+    fn it_iterates_in_a_deterministic_order() {
+        let lua = FactorioLua::new().unwrap();
+
+        let raw: Table = lua.run_script("test", "raw = {} return raw").unwrap();
+        lua.track_insertion_order(raw, 2).unwrap();
+
+        let keys: String = lua
+            .run_script(
+                "test",
+                r#"
+                raw.recipe = {}
+                raw.item = {}
+                raw.item.zinc = 1
+                raw.item.copper = 2
+                raw.item.iron = 3
+                raw.item.copper = nil
+                raw.item.copper = 4
+
+                local keys = {}
+                for k in pairs(raw) do keys[#keys + 1] = k end
+                for k in next, raw.item do keys[#keys + 1] = k end
+                for k in pairs({ b = 1, a = 2, "x", [10] = 3, [false] = 4 }) do
+                    keys[#keys + 1] = tostring(k)
+                end
+
+                -- Iterations with `next` that restart see keys added in between.
+                local t = { c = 1, b = 2 }
+                assert(next(t) == "b")
+                t.a = 3
+                for k in next, t do keys[#keys + 1] = k end
+                assert(next({}) == nil)
+                local n = 0
+                for _ in next, { [{}] = 1, [{}] = 2, [print] = 3 } do n = n + 1 end
+                assert(n == 3)
+
+                return table.concat(keys, ",")
+                "#,
+            )
+            .unwrap();
+        assert_eq!(keys, "recipe,item,zinc,iron,copper,1,10,a,b,false,a,b,c");
+    }
+
+    #[test]
+    fn it_hides_the_tracking_metatable() {
+        let lua = FactorioLua::new().unwrap();
+
+        let raw: Table = lua.run_script("test", "raw = {} return raw").unwrap();
+        lua.track_insertion_order(raw, 2).unwrap();
+
+        let keys: String = lua
+            .run_script(
+                "test",
+                r#"
+                assert(getmetatable(raw) == nil)
+
+                -- A mod's metatable is visible and tracking continues.
+                local mt = { __index = function(_, k) return "default-" .. k end }
+                assert(setmetatable(raw, mt) == raw)
+                assert(getmetatable(raw) == mt)
+                assert(raw.missing == "default-missing")
+                raw.b = {}
+                raw.a = {}
+                raw.b.y = 1
+                raw.b.x = 2
+
+                -- The mod's `__newindex` is still called.
+                local set = {}
+                setmetatable(raw, { __newindex = function(t, k, v) set[#set + 1] = k rawset(t, k, v) end })
+                raw.c = {}
+                assert(set[1] == "c")
+
+                setmetatable(raw, { __metatable = "locked" })
+                assert(getmetatable(raw) == "locked")
+                assert(not pcall(setmetatable, raw, {}))
+
+                local keys = {}
+                for k in pairs(raw) do keys[#keys + 1] = k end
+                for k in pairs(raw.b) do keys[#keys + 1] = k end
+                return table.concat(keys, ",")
+                "#,
+            )
+            .unwrap();
+        assert_eq!(keys, "b,a,c,y,x");
+    }
+
+    #[test]
+    fn it_extracts_mod_names_from_sources() {
+        assert_eq!(
+            mod_name_from_source("__base__/prototypes/item.lua"),
+            Some("base")
+        );
+        assert_eq!(mod_name_from_source("data.lua"), None);
+    }
+}
@@ -0,0 +1,312 @@
+//! Mod migrations, which rename prototypes between versions of a mod.
+//!
+//! Mods ship them as `migrations/*.json` files, e.g.
+//!
+//! ```json
+//! {
+//!   "entity": [["old-furnace", "new-furnace"]],
+//!   "item": [["old-furnace", "new-furnace"]]
+//! }
+//! ```
+//!
+//! The game applies the migrations of all mods in load order, and the files of
+//! a mod sorted by name. Lua migrations only change the game state and are
+//! ignored.
+
+use std::collections::BTreeMap;
+
+use rustorio_blueprint::{
+    types::{
+        SignalID,
+        SignalType,
+    },
+    Blueprint,
+    BlueprintBook,
+    Entity,
+};
+use rustorio_prototype::{
+    Id,
+    Prototype,
+};
+
+use crate::{
+    Error,
+    Mod,
+};
+
+/// A single migration file.
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub mod_name: String,
+
+    /// The file name, e.g. `2.0.0.json`.
+    pub file_name: String,
+
+    /// The renames by category, as pairs of old and new name.
+    pub renames: BTreeMap<String, Vec<(String, String)>>,
+}
+
+impl Migration {
+    pub fn parse(mod_name: &str, file_name: &str, data: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            mod_name: mod_name.to_owned(),
+            file_name: file_name.to_owned(),
+            renames: serde_json::from_slice(data)?,
+        })
+    }
+
+    pub fn rename(&self, category: &str, name: &str) -> Option<&str> {
+        self.renames
+            .get(category)?
+            .iter()
+            .find(|(old, _)| old == name)
+            .map(|(_, new)| new.as_str())
+    }
+}
+
+/// The migrations of all loaded mods, see
+/// [`Loader::migrations`](crate::Loader::migrations).
+#[derive(Clone, Debug, Default)]
+pub struct Migrations {
+    pub migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    /// Reads the JSON migrations of a mod and adds them.
+    pub(crate) fn add_mod(&mut self, fmod: &Mod) -> Result<(), Error> {
+        if !fmod.files.exists("migrations") {
+            return Ok(());
+        }
+
+        let mut files = fmod
+            .files
+            .list_dir("migrations")?
+            .into_iter()
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .filter_map(|path| {
+                let file_name = path.file_name()?.to_str()?.to_owned();
+                Some((file_name, path))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+
+        for (file_name, path) in files {
+            log::debug!(
+                "Reading migration: __{}__/migrations/{}",
+                fmod.name(),
+                file_name
+            );
+            let data = fmod.files.read(&path)?;
+            self.migrations
+                .push(Migration::parse(fmod.name(), &file_name, &data)?);
+        }
+
+        Ok(())
+    }
+
+    /// Applies all migrations to a name, in order. A name can be renamed
+    /// several times.
+    pub fn rename(&self, category: &str, name: &str) -> Option<String> {
+        let mut renamed = None;
+        for migration in &self.migrations {
+            let current = renamed.as_deref().unwrap_or(name);
+            if let Some(new) = migration.rename(category, current) {
+                renamed = Some(new.to_owned());
+            }
+        }
+        renamed.filter(|new| new != name)
+    }
+
+    fn migrate_name(&self, category: &str, name: &mut String) {
+        if let Some(new) = self.rename(category, name) {
+            *name = new;
+        }
+    }
+
+    /// Migrates an id by the [`Prototype::CATEGORY`] of its type, e.g. all
+    /// entities by the category `entity`.
+    pub fn migrate_id<P: Prototype>(&self, id: &Id<P>) -> Id<P> {
+        let Some(category) = P::CATEGORY
+        else {
+            return id.clone();
+        };
+        match self.rename(category, id.as_str()) {
+            Some(new) => new.into(),
+            None => id.clone(),
+        }
+    }
+
+    fn migrate_signal(&self, signal: &mut SignalID) {
+        let category = match signal.r#type {
+            SignalType::Item => "item",
+            SignalType::Fluid => "fluid",
+            SignalType::Virtual => "virtual-signal",
+        };
+        self.migrate_name(category, &mut signal.name);
+    }
+
+    fn migrate_entity(&self, entity: &mut Entity) {
+        self.migrate_name("entity", &mut entity.name);
+
+        if let Some(recipe) = &mut entity.recipe {
+            self.migrate_name("recipe", recipe);
+        }
+        if let Some(filter) = &mut entity.filter {
+            self.migrate_name("item", filter);
+        }
+        for filter in &mut entity.filters {
+            self.migrate_name("item", &mut filter.name);
+        }
+        for filter in &mut entity.request_filters {
+            self.migrate_name("item", &mut filter.name);
+        }
+        if let Some(inventory) = &mut entity.inventory {
+            for filter in &mut inventory.filters {
+                self.migrate_name("item", &mut filter.name);
+            }
+        }
+        if let Some(infinity_settings) = &mut entity.infinity_settings {
+            for filter in &mut infinity_settings.filters {
+                self.migrate_name("item", &mut filter.name);
+            }
+        }
+
+        if let Some(items) = &mut entity.items {
+            let mut migrated = BTreeMap::new();
+            for (mut name, count) in std::mem::take(items) {
+                self.migrate_name("item", &mut name);
+                *migrated.entry(name).or_default() += count;
+            }
+            *items = migrated;
+        }
+
+        if let Some(control_behavior) = &mut entity.control_behavior {
+            for filter in &mut control_behavior.filters {
+                self.migrate_signal(&mut filter.signal);
+            }
+            if let Some(conditions) = &mut control_behavior.arithmetic_conditions {
+                for signal in [
+                    &mut conditions.first_signal,
+                    &mut conditions.second_signal,
+                    &mut conditions.output_signal,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    self.migrate_signal(signal);
+                }
+            }
+            if let Some(conditions) = &mut control_behavior.decider_conditions {
+                for signal in [
+                    &mut conditions.first_signal,
+                    &mut conditions.second_signal,
+                    &mut conditions.output_signal,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    self.migrate_signal(signal);
+                }
+            }
+            if let Some(signal) = control_behavior
+                .circuit_condition
+                .as_mut()
+                .and_then(|condition| condition.first_signal.as_mut())
+            {
+                self.migrate_signal(signal);
+            }
+        }
+
+        if let Some(signal) = entity
+            .alert_parameters
+            .as_mut()
+            .and_then(|parameters| parameters.icon_signal_id.as_mut())
+        {
+            self.migrate_signal(signal);
+        }
+    }
+
+    /// Renames the entities, tiles and icons of a blueprint, and the items,
+    /// recipes and signals its entities are configured with.
+    pub fn migrate_blueprint(&self, blueprint: &mut Blueprint) {
+        for entity in &mut blueprint.entities {
+            self.migrate_entity(entity);
+        }
+        for tile in &mut blueprint.tiles {
+            self.migrate_name("tile", &mut tile.name);
+        }
+        for icon in &mut blueprint.icons {
+            self.migrate_signal(&mut icon.signal);
+        }
+    }
+
+    pub fn migrate_blueprint_book(&self, book: &mut BlueprintBook) {
+        for blueprint in book.blueprints.values_mut() {
+            self.migrate_blueprint(blueprint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustorio_blueprint::{
+        types::Position,
+        Tile,
+    };
+    use rustorio_prototype::{
+        entity::AssemblingMachinePrototype,
+        item::{
+            ArmorPrototype,
+            ItemPrototype,
+        },
+        recipe::RecipeCategory,
+    };
+
+    use super::*;
+
+    fn migrations() -> Migrations {
+        let migration =
+            |file_name, json: &str| Migration::parse("foo", file_name, json.as_bytes()).unwrap();
+        Migrations {
+            migrations: vec![
+                migration(
+                    "1.0.0.json",
+                    r#"{"entity": [["old-assembler", "assembler"]], "tile": [["old-concrete", "concrete"]]}"#,
+                ),
+                migration(
+                    "2.0.0.json",
+                    r#"{"entity": [["assembler", "fast-assembler"]], "item": [["old-plate", "plate"]]}"#,
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn it_migrates_ids_and_blueprints() {
+        let migrations = migrations();
+
+        let id: Id<AssemblingMachinePrototype> = "old-assembler".into();
+        assert_eq!(migrations.migrate_id(&id).as_str(), "fast-assembler");
+        let id: Id<ItemPrototype> = "old-assembler".into();
+        assert_eq!(migrations.migrate_id(&id).as_str(), "old-assembler");
+        assert_eq!(ArmorPrototype::CATEGORY, Some("item"));
+        assert_eq!(RecipeCategory::CATEGORY, Some("recipe-category"));
+
+        let mut entity = Entity::new(1, "old-assembler".to_owned(), Position::new(0., 0.));
+        entity.items = Some([("old-plate".to_owned(), 2)].into_iter().collect());
+        let mut blueprint = Blueprint {
+            entities: vec![entity],
+            tiles: vec![Tile {
+                name: "old-concrete".to_owned(),
+                position: Position::new(0., 0.),
+            }],
+            ..Default::default()
+        };
+        migrations.migrate_blueprint(&mut blueprint);
+
+        let entity = &blueprint.entities[0];
+        assert_eq!(entity.name, "fast-assembler");
+        assert!(entity.items.as_ref().unwrap().contains_key("plate"));
+        assert_eq!(blueprint.tiles[0].name, "concrete");
+    }
+}
@@ -0,0 +1,292 @@
+-- Deterministic replacements for `next` and `pairs`.
+--
+-- Stock Lua iterates tables in hash order, which even changes between runs. These
+-- functions make the iteration order deterministic. This is not the game's `pairs`,
+-- which iterates every table in insertion order: we can't observe every insertion, so
+-- only tables registered with `track` (`data.raw` and its type tables) are iterated in
+-- insertion order. Tables are iterated in this order:
+--
+--   1. The array part, `1..#t`.
+--   2. For tracked tables, the keys in the order they were inserted.
+--   3. All other keys, sorted: numbers, then strings, then booleans. Other keys (e.g.
+--      tables) come last, in hash order.
+--
+-- A table is tracked by setting a `__newindex` metamethod on it, so keys that are set
+-- with `rawset` are not tracked. The replacements for `getmetatable` and
+-- `setmetatable` hide that metatable from mods: `getmetatable` returns the metatable a
+-- mod set, and `setmetatable` keeps tracking the table, using a copy of the mod's
+-- metatable. Changes to that metatable after `setmetatable` are therefore not seen.
+-- `debug.getmetatable` and `debug.setmetatable` are not replaced.
+
+local raw_next = next
+local rawget, rawset, rawlen = rawget, rawset, rawlen
+local getmetatable, setmetatable = getmetatable, setmetatable
+local type, error, select = type, error, select
+local sort = table.sort
+
+-- Marks a key that was removed from the insertion order, because it was inserted again.
+local removed = {}
+
+-- Insertion order of tracked tables: `{ keys = { ... }, pos = { [key] = index } }`
+local tracked = setmetatable({}, { __mode = "k" })
+
+-- Iteration order that `next` uses, built when an iteration starts.
+local snapshots = setmetatable({}, { __mode = "k" })
+
+local type_order = { number = 1, string = 2, boolean = 3 }
+
+local function compare_keys(a, b)
+  local ta, tb = type_order[type(a)] or 4, type_order[type(b)] or 4
+  if ta ~= tb then
+    return ta < tb
+  elseif ta == 3 then
+    return not a and b
+  elseif ta == 4 then
+    return false
+  end
+  return a < b
+end
+
+local function order(t)
+  local keys, seen = {}, {}
+
+  for i = 1, rawlen(t) do
+    keys[i] = i
+    seen[i] = true
+  end
+
+  local insertion_order = tracked[t]
+  if insertion_order then
+    local inserted = insertion_order.keys
+    for i = 1, #inserted do
+      local k = inserted[i]
+      if k ~= removed and not seen[k] and rawget(t, k) ~= nil then
+        keys[#keys + 1] = k
+        seen[k] = true
+      end
+    end
+  end
+
+  local rest = {}
+  for k in raw_next, t do
+    if not seen[k] then
+      rest[#rest + 1] = k
+    end
+  end
+  sort(rest, compare_keys)
+  for i = 1, #rest do
+    keys[#keys + 1] = rest[i]
+  end
+
+  return keys
+end
+
+local function record(insertion_order, k)
+  local keys, pos = insertion_order.keys, insertion_order.pos
+  local i = pos[k]
+  if i then
+    keys[i] = removed
+  end
+  keys[#keys + 1] = k
+  pos[k] = #keys
+end
+
+-- The first key in iteration order, without sorting all keys, or `nil` if it can't be
+-- determined that way because the smallest key isn't a number, string or boolean.
+-- `next(t) == nil` checks are common and shouldn't sort the table each time.
+local function first_key(t)
+  if rawlen(t) > 0 then
+    return 1
+  end
+
+  local insertion_order = tracked[t]
+  if insertion_order then
+    local inserted = insertion_order.keys
+    for i = 1, #inserted do
+      local k = inserted[i]
+      if k ~= removed and rawget(t, k) ~= nil then
+        return k
+      end
+    end
+  end
+
+  local first = raw_next(t)
+  for k in raw_next, t, first do
+    if compare_keys(k, first) then
+      first = k
+    end
+  end
+  if type_order[type(first)] then
+    return first
+  end
+  return nil
+end
+
+local function ordered_next(t, k)
+  if k == nil then
+    -- A new iteration starts, so the snapshot of the last one is outdated.
+    snapshots[t] = nil
+    if raw_next(t) == nil then
+      return nil
+    end
+    local first = first_key(t)
+    if first ~= nil then
+      return first, rawget(t, first)
+    end
+  end
+
+  local snapshot = snapshots[t]
+  local i = 0
+
+  if k ~= nil then
+    i = snapshot and snapshot.pos[k]
+  end
+
+  if k == nil or not i then
+    local keys = order(t)
+    local pos = {}
+    for j = 1, #keys do
+      pos[keys[j]] = j
+    end
+    snapshot = { keys = keys, pos = pos }
+    snapshots[t] = snapshot
+
+    if k == nil then
+      i = 0
+    else
+      i = pos[k]
+      if not i then
+        error("invalid key to 'next'", 2)
+      end
+    end
+  end
+
+  local keys = snapshot.keys
+  for j = i + 1, #keys do
+    local key = keys[j]
+    local v = rawget(t, key)
+    if v ~= nil then
+      return key, v
+    end
+  end
+  return nil
+end
+
+local function ordered_pairs(t)
+  -- A protected metatable returns its `__metatable` field, which needn't be a table.
+  local mt = getmetatable(t)
+  if type(mt) == "table" and mt.__pairs then
+    return mt.__pairs(t)
+  end
+
+  local keys = order(t)
+  local i = 0
+  local function iter()
+    while true do
+      i = i + 1
+      local k = keys[i]
+      if k == nil then
+        return nil
+      end
+      local v = rawget(t, k)
+      if v ~= nil then
+        return k, v
+      end
+    end
+  end
+  return iter, t, nil
+end
+
+local track
+
+-- The metatable of a tracked table: a copy of `mt`, the metatable set by a mod, with a
+-- `__newindex` that records the insertion before calling the mod's `__newindex`.
+local function tracking_metatable(insertion_order, mt)
+  local depth = insertion_order.depth
+  local newindex = mt and rawget(mt, "__newindex")
+
+  local combined = {}
+  if mt then
+    for k, v in raw_next, mt do
+      combined[k] = v
+    end
+  end
+
+  combined.__newindex = function(t, k, v)
+    if newindex == nil then
+      rawset(t, k, v)
+    elseif type(newindex) == "function" then
+      newindex(t, k, v)
+    else
+      newindex[k] = v
+    end
+    if rawget(t, k) ~= nil then
+      record(insertion_order, k)
+      if depth > 1 and type(v) == "table" then
+        track(v, depth - 1)
+      end
+    end
+  end
+
+  return combined
+end
+
+-- Tracks the insertion order of `t`, and of tables inserted into it, up to `depth`
+-- levels deep. Keys that already exist are ordered as if they were inserted in
+-- iteration order. Tables that have a metatable are not tracked.
+function track(t, depth)
+  if tracked[t] or getmetatable(t) ~= nil then
+    return
+  end
+
+  local insertion_order = { keys = {}, pos = {}, depth = depth }
+  local keys = order(t)
+  for i = 1, #keys do
+    record(insertion_order, keys[i])
+    local v = rawget(t, keys[i])
+    if depth > 1 and type(v) == "table" then
+      track(v, depth - 1)
+    end
+  end
+  tracked[t] = insertion_order
+
+  setmetatable(t, tracking_metatable(insertion_order, nil))
+end
+
+local function ordered_getmetatable(t)
+  local insertion_order = tracked[t]
+  if not insertion_order then
+    return getmetatable(t)
+  end
+
+  local mt = insertion_order.metatable
+  if mt ~= nil and rawget(mt, "__metatable") ~= nil then
+    return rawget(mt, "__metatable")
+  end
+  return mt
+end
+
+local function ordered_setmetatable(...)
+  local t, mt = ...
+  local insertion_order = tracked[t]
+  if not insertion_order or select("#", ...) < 2 or (mt ~= nil and type(mt) ~= "table") then
+    return setmetatable(...)
+  end
+
+  local old = insertion_order.metatable
+  if old ~= nil and rawget(old, "__metatable") ~= nil then
+    error("cannot change a protected metatable", 2)
+  end
+
+  insertion_order.metatable = mt
+  setmetatable(t, tracking_metatable(insertion_order, mt))
+  return t
+end
+
+return {
+  next = ordered_next,
+  pairs = ordered_pairs,
+  getmetatable = ordered_getmetatable,
+  setmetatable = ordered_setmetatable,
+  track = track,
+}
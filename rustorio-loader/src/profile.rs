@@ -0,0 +1,245 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use mlua::Lua;
+use serde_json::json;
+
+use crate::lua::mod_name_from_source;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpanKind {
+    /// A stage file, like `data.lua` or `settings-updates.lua`.
+    File,
+
+    /// A module loaded by `require`.
+    Module,
+}
+
+/// A single run of a file.
+#[derive(Clone, Debug)]
+pub struct Span {
+    /// The file as the game displays it, e.g. `__base__/data.lua`.
+    pub name: String,
+
+    pub mod_name: String,
+
+    pub kind: SpanKind,
+
+    /// How many spans this one is nested in.
+    pub depth: usize,
+
+    /// When the file started running, relative to the start of the profile.
+    pub start: Duration,
+
+    /// The wall time, including nested modules.
+    pub duration: Duration,
+
+    /// The memory used by the Lua state before and after the file ran.
+    pub memory_before: usize,
+
+    pub memory_after: usize,
+}
+
+impl Span {
+    /// How much the memory used by the Lua state grew. This can be negative
+    /// if the garbage collector ran.
+    pub fn memory_growth(&self) -> i64 {
+        self.memory_after as i64 - self.memory_before as i64
+    }
+}
+
+#[derive(Debug)]
+struct OpenSpan {
+    name: String,
+    kind: SpanKind,
+    start: Instant,
+    memory_before: usize,
+}
+
+/// Records how long each file of the settings and data stages runs. See
+/// [`Loader::data_stage_profiled`](crate::Loader::data_stage_profiled).
+///
+/// While a stage runs, the profiler is stored in the Lua state's app data, so
+/// that `require` can record modules.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    start: Option<Instant>,
+    open: Vec<OpenSpan>,
+    spans: Vec<Span>,
+}
+
+impl Profiler {
+    /// Moves the profiler into the Lua state.
+    pub(crate) fn attach(&mut self, lua: &Lua) {
+        self.start.get_or_insert_with(Instant::now);
+        lua.set_app_data(std::mem::take(self));
+    }
+
+    /// Moves the profiler out of the Lua state again.
+    pub(crate) fn detach(&mut self, lua: &Lua) {
+        if let Some(profiler) = lua.remove_app_data::<Self>() {
+            *self = profiler;
+        }
+    }
+
+    /// Starts a span, if a profiler is attached to the Lua state.
+    pub(crate) fn enter(lua: &Lua, kind: SpanKind, name: &str) {
+        let memory_before = lua.used_memory();
+        if let Some(mut profiler) = lua.app_data_mut::<Self>() {
+            profiler.open.push(OpenSpan {
+                name: name.to_owned(),
+                kind,
+                start: Instant::now(),
+                memory_before,
+            });
+        }
+    }
+
+    /// Ends the innermost span with the given name. Spans nested in it are
+    /// ended too, e.g. modules that raised an error which was caught.
+    pub(crate) fn exit(lua: &Lua, name: &str) {
+        let memory_after = lua.used_memory();
+        let Some(mut profiler) = lua.app_data_mut::<Self>()
+        else {
+            return;
+        };
+        if !profiler.open.iter().any(|span| span.name == name) {
+            return;
+        }
+
+        let end = Instant::now();
+        let profile_start = profiler.start.unwrap_or(end);
+        while let Some(open) = profiler.open.pop() {
+            let is_done = open.name == name;
+            let span = Span {
+                mod_name: mod_name_from_source(&open.name)
+                    .unwrap_or("core")
+                    .to_owned(),
+                name: open.name,
+                kind: open.kind,
+                depth: profiler.open.len(),
+                start: open.start.saturating_duration_since(profile_start),
+                duration: end.saturating_duration_since(open.start),
+                memory_before: open.memory_before,
+                memory_after,
+            };
+            profiler.spans.push(span);
+            if is_done {
+                break;
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Profile {
+        self.spans.sort_by_key(|span| (span.start, span.depth));
+        Profile { spans: self.spans }
+    }
+}
+
+/// The time and memory used by a mod, see [`Profile::by_mod`].
+#[derive(Clone, Debug, Default)]
+pub struct ModProfile {
+    pub duration: Duration,
+    pub memory_growth: i64,
+    pub files: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// All spans, in the order they started.
+    pub spans: Vec<Span>,
+}
+
+impl Profile {
+    /// Sums up the time and memory of the stage files of each mod. Modules
+    /// are counted for the mod whose file required them.
+    pub fn by_mod(&self) -> BTreeMap<&str, ModProfile> {
+        let mut mods = BTreeMap::<&str, ModProfile>::new();
+        for span in &self.spans {
+            if span.depth == 0 {
+                let profile = mods.entry(&span.mod_name).or_default();
+                profile.duration += span.duration;
+                profile.memory_growth += span.memory_growth();
+                profile.files += 1;
+            }
+        }
+        mods
+    }
+
+    /// Writes the mods and then the files that took the longest, as a plain
+    /// text table.
+    pub fn write_table(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut mods = self.by_mod().into_iter().collect::<Vec<_>>();
+        mods.sort_by_key(|(_, profile)| std::cmp::Reverse(profile.duration));
+
+        writeln!(
+            writer,
+            "{:>10} {:>12} {:>6}  mod",
+            "time (ms)", "memory (KiB)", "files"
+        )?;
+        for (mod_name, profile) in mods {
+            writeln!(
+                writer,
+                "{:>10.1} {:>12} {:>6}  {}",
+                profile.duration.as_secs_f64() * 1000.,
+                profile.memory_growth / 1024,
+                profile.files,
+                mod_name,
+            )?;
+        }
+
+        let mut spans = self.spans.iter().collect::<Vec<_>>();
+        spans.sort_by_key(|span| std::cmp::Reverse(span.duration));
+
+        writeln!(writer)?;
+        writeln!(writer, "{:>10} {:>12}  file", "time (ms)", "memory (KiB)")?;
+        for span in spans {
+            writeln!(
+                writer,
+                "{:>10.1} {:>12}  {}{}",
+                span.duration.as_secs_f64() * 1000.,
+                span.memory_growth() / 1024,
+                "  ".repeat(span.depth),
+                span.name,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the spans in the Chrome trace event format, which can be viewed
+    /// with `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+    pub fn write_chrome_trace(&self, writer: impl Write) -> Result<(), serde_json::Error> {
+        let events = self
+            .spans
+            .iter()
+            .map(|span| {
+                json!({
+                    "name": span.name,
+                    "cat": match span.kind {
+                        SpanKind::File => "file",
+                        SpanKind::Module => "module",
+                    },
+                    "ph": "X",
+                    "ts": span.start.as_micros() as u64,
+                    "dur": span.duration.as_micros() as u64,
+                    "pid": 1,
+                    "tid": 1,
+                    "args": {
+                        "mod": span.mod_name,
+                        "memory_before": span.memory_before,
+                        "memory_after": span.memory_after,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::to_writer(writer, &json!({ "traceEvents": events }))
+    }
+}
@@ -23,6 +23,8 @@ pub enum PropertyType {
     String,
     List,
     Dictionary,
+    SignedInteger,
+    UnsignedInteger,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -40,6 +42,8 @@ impl TryFrom<u8> for PropertyType {
             3 => Ok(Self::String),
             4 => Ok(Self::List),
             5 => Ok(Self::Dictionary),
+            6 => Ok(Self::SignedInteger),
+            7 => Ok(Self::UnsignedInteger),
             _ => Err(InvalidPropertyType(b)),
         }
     }
@@ -54,6 +58,8 @@ impl From<PropertyType> for u8 {
             PropertyType::String => 3,
             PropertyType::List => 4,
             PropertyType::Dictionary => 5,
+            PropertyType::SignedInteger => 6,
+            PropertyType::UnsignedInteger => 7,
         }
     }
 }
@@ -120,6 +126,14 @@ impl<'de> Deserializer<'de> {
     fn read_u32(&mut self) -> Result<u32, Error> {
         Ok(LittleEndian::read_u32(self.read_bytes(4)?))
     }
+
+    fn read_i64(&mut self) -> Result<i64, Error> {
+        Ok(LittleEndian::read_i64(self.read_bytes(8)?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        Ok(LittleEndian::read_u64(self.read_bytes(8)?))
+    }
 }
 
 impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
@@ -139,7 +153,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             PropertyType::Bool => visitor.visit_bool(self.read_bool()?),
             PropertyType::Number => visitor.visit_f64(self.read_double()?),
             PropertyType::String => visitor.visit_str(self.read_string()?),
-            PropertyType::List | PropertyType::Dictionary => {
+            PropertyType::List => {
+                let n = self.read_u32()?;
+                log::trace!("Reading list with {} entries...", n);
+                visitor.visit_seq(&mut SeqAccess {
+                    n: n as usize,
+                    de: self,
+                })
+            }
+            PropertyType::Dictionary => {
                 let n = self.read_u32()?;
                 log::trace!("Reading map with {} entries...", n);
                 visitor.visit_map(&mut MapAccess {
@@ -147,6 +169,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                     de: self,
                 })
             }
+            PropertyType::SignedInteger => visitor.visit_i64(self.read_i64()?),
+            PropertyType::UnsignedInteger => visitor.visit_u64(self.read_u64()?),
         }
     }
 
@@ -208,3 +232,33 @@ impl<'de, 'a> de::MapAccess<'de> for MapAccess<'de, 'a> {
         Ok(seed.deserialize(&mut *self.de)?)
     }
 }
+
+pub struct SeqAccess<'de, 'a> {
+    n: usize,
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'de, 'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.n > 0 {
+            self.n -= 1;
+
+            // List items have keys too, but they're always empty.
+            self.de.read_string()?;
+
+            Ok(Some(seed.deserialize(&mut *self.de)?))
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.n)
+    }
+}
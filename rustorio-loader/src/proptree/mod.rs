@@ -1,12 +1,17 @@
 pub mod de;
+pub mod ser;
 pub mod value;
 
 use std::fmt::Display;
 
-use serde::Deserialize;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
 pub use self::{
     de::Deserializer,
+    ser::Serializer,
     value::Value,
 };
 
@@ -26,6 +31,15 @@ where
     }
 }
 
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_vec())
+}
+
 use self::de::InvalidPropertyType;
 
 #[derive(Debug, thiserror::Error)]
@@ -54,3 +68,9 @@ impl serde::de::Error for Error {
         Self::Custom(format!("{}", msg))
     }
 }
+
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(format!("{}", msg))
+    }
+}
@@ -1,11 +1,20 @@
 use std::collections::HashMap;
 
-use serde::de::{
-    self,
-    Deserialize,
-    Deserializer,
-    MapAccess,
-    Visitor,
+use serde::{
+    de::{
+        self,
+        Deserialize,
+        Deserializer,
+        MapAccess,
+        SeqAccess,
+        Visitor,
+    },
+    ser::{
+        Serialize,
+        SerializeMap,
+        SerializeSeq,
+        Serializer,
+    },
 };
 
 #[derive(Clone, Debug)]
@@ -14,8 +23,40 @@ pub enum Value {
     Bool(bool),
     Number(f64),
     String(String),
-    //List(Vec<Value>),
+    List(Vec<Value>),
     Dictionary(HashMap<String, Value>),
+    SignedInteger(i64),
+    UnsignedInteger(u64),
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::None => serializer.serialize_none(),
+            Value::Bool(x) => serializer.serialize_bool(*x),
+            Value::Number(x) => serializer.serialize_f64(*x),
+            Value::String(x) => serializer.serialize_str(x),
+            Value::List(x) => {
+                let mut seq = serializer.serialize_seq(Some(x.len()))?;
+                for value in x {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::Dictionary(x) => {
+                let mut map = serializer.serialize_map(Some(x.len()))?;
+                for (key, value) in x {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::SignedInteger(x) => serializer.serialize_i64(*x),
+            Value::UnsignedInteger(x) => serializer.serialize_u64(*x),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Value {
@@ -44,6 +85,14 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::Number(v))
     }
 
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::SignedInteger(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::UnsignedInteger(v))
+    }
+
     fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
         Ok(Value::String(v.to_owned()))
     }
@@ -56,6 +105,14 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::None)
     }
 
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut v = vec![];
+        while let Some(value) = seq.next_element()? {
+            v.push(value);
+        }
+        Ok(Value::List(v))
+    }
+
     fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
         let mut v = HashMap::new();
         while let Some(key) = map.next_key()? {
@@ -0,0 +1,542 @@
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+        HashSet,
+    },
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    Dependency,
+    DependencyError,
+    Error,
+    Mod,
+    Version,
+};
+
+/// The contents of `mod-list.json`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ModList {
+    pub mods: Vec<ModListEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModListEntry {
+    pub name: String,
+
+    pub enabled: bool,
+
+    /// Pins the mod to a specific version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl ModList {
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ModListEntry> {
+        self.mods.iter().find(|entry| entry.name == name)
+    }
+
+    /// Whether a mod is enabled. Like the game does with newly installed mods,
+    /// mods that are not listed are enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.get(name).is_none_or(|entry| entry.enabled)
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(entry) = self.mods.iter_mut().find(|entry| entry.name == name) {
+            entry.enabled = enabled;
+        }
+        else {
+            self.mods.push(ModListEntry {
+                name: name.to_owned(),
+                enabled,
+                version: None,
+            });
+        }
+    }
+}
+
+/// Explains why there is no selection of mods that satisfies all
+/// dependencies.
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub lines: Vec<String>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))
+    }
+}
+
+/// Selects a version for every enabled mod and sorts the selected mods into
+/// the order in which the game loads them: By dependency depth, then by name.
+pub(crate) fn resolve(available: Vec<Mod>, mod_list: Option<&ModList>) -> Result<Vec<Mod>, Error> {
+    let mut by_name: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut disabled = HashSet::new();
+    for (i, fmod) in available.iter().enumerate() {
+        if mod_list.is_none_or(|mod_list| mod_list.is_enabled(fmod.name())) {
+            by_name.entry(fmod.name().to_owned()).or_default().push(i);
+        }
+        else {
+            log::debug!("Skipping disabled mod: {} {}", fmod.name(), fmod.version());
+            disabled.insert(fmod.name().to_owned());
+        }
+    }
+
+    let mut names = vec![];
+    let mut candidates = vec![];
+    for (name, mut versions) in by_name {
+        // Newest version first.
+        versions.sort_by_key(|i| std::cmp::Reverse(available[*i].version()));
+        versions.dedup_by_key(|i| available[*i].version());
+
+        let pinned = mod_list
+            .and_then(|mod_list| mod_list.get(&name))
+            .and_then(|entry| entry.version.as_ref());
+        if let Some(pinned) = pinned {
+            let pinned: Version = pinned.parse()?;
+            let installed = format_versions(&available, &versions);
+            versions.retain(|i| available[*i].version() == pinned);
+            if versions.is_empty() {
+                let explanation = Explanation {
+                    lines: vec![format!(
+                        "{} is pinned to version {} in mod-list.json, but only {} is installed",
+                        name, pinned, installed
+                    )],
+                };
+                return Err(DependencyError::Unsatisfiable(explanation).into());
+            }
+        }
+
+        names.push(name);
+        candidates.push(versions);
+    }
+
+    let mut resolver = Resolver {
+        available: &available,
+        names: &names,
+        index: names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect(),
+        candidates,
+        disabled,
+        selected: vec![],
+        rejected: vec![],
+        failure: None,
+    };
+
+    if !resolver.search(0) {
+        let (_, explanation) = resolver
+            .failure
+            .expect("search failed without an explanation");
+        return Err(DependencyError::Unsatisfiable(explanation).into());
+    }
+
+    let order = load_order(&available, &resolver.selected)?;
+
+    let mut available = available.into_iter().map(Some).collect::<Vec<_>>();
+    Ok(order
+        .into_iter()
+        .map(|i| available[i].take().unwrap())
+        .collect())
+}
+
+fn format_versions(available: &[Mod], versions: &[usize]) -> String {
+    versions
+        .iter()
+        .map(|i| available[*i].version().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+struct Rejection {
+    reason: String,
+
+    /// The mod whose selected version caused the rejection, as an index into
+    /// `Resolver::names`.
+    related: Option<usize>,
+}
+
+/// Backtracking search for a version of each mod, trying the newest versions
+/// first.
+struct Resolver<'a> {
+    available: &'a [Mod],
+
+    names: &'a [String],
+
+    index: HashMap<&'a str, usize>,
+
+    /// The versions of each mod that can be selected, as indices into
+    /// `available`.
+    candidates: Vec<Vec<usize>>,
+
+    disabled: HashSet<String>,
+
+    /// The versions selected so far.
+    selected: Vec<usize>,
+
+    /// Why the versions newer than the selected ones were rejected.
+    rejected: Vec<Vec<Rejection>>,
+
+    /// The deepest point at which the search failed.
+    failure: Option<(usize, Explanation)>,
+}
+
+impl<'a> Resolver<'a> {
+    fn search(&mut self, i: usize) -> bool {
+        if i == self.names.len() {
+            return true;
+        }
+
+        let available = self.available;
+        let mut rejected = vec![];
+
+        for k in self.candidates[i].clone() {
+            let candidate = &available[k];
+
+            match self.check(i, candidate) {
+                Err(rejection) => rejected.push(rejection),
+                Ok(()) => {
+                    self.selected.push(k);
+                    self.rejected.push(rejected);
+
+                    if self.search(i + 1) {
+                        return true;
+                    }
+
+                    self.selected.pop();
+                    rejected = self.rejected.pop().unwrap();
+                    rejected.push(Rejection {
+                        reason: format!(
+                            "{} {} can't be combined with the remaining mods",
+                            candidate.name(),
+                            candidate.version()
+                        ),
+                        related: None,
+                    });
+                }
+            }
+        }
+
+        if self.failure.as_ref().is_none_or(|(depth, _)| i > *depth) {
+            self.failure = Some((i, self.explain(i, &rejected)));
+        }
+
+        false
+    }
+
+    /// Checks whether `candidate` can be selected for `names[i]`, given the
+    /// versions selected so far.
+    fn check(&self, i: usize, candidate: &Mod) -> Result<(), Rejection> {
+        for dependency in candidate.dependencies() {
+            if dependency.mod_name() == "core" {
+                continue;
+            }
+
+            match self.index.get(dependency.mod_name()) {
+                None => {
+                    if dependency.prefix().is_required() {
+                        let state = if self.disabled.contains(dependency.mod_name()) {
+                            "disabled"
+                        }
+                        else {
+                            "not installed"
+                        };
+                        return Err(Rejection {
+                            reason: format!(
+                                "{} {} depends on `{}`, but {} is {}",
+                                candidate.name(),
+                                candidate.version(),
+                                dependency,
+                                dependency.mod_name(),
+                                state
+                            ),
+                            related: None,
+                        });
+                    }
+                }
+                Some(&j) if j < i => {
+                    let other = &self.available[self.selected[j]];
+                    if !dependency.allows(&other.version()) {
+                        return Err(Rejection {
+                            reason: format!(
+                                "{} {} depends on `{}`, but {} {} is selected",
+                                candidate.name(),
+                                candidate.version(),
+                                dependency,
+                                other.name(),
+                                other.version()
+                            ),
+                            related: Some(j),
+                        });
+                    }
+                }
+                Some(&j) => {
+                    let satisfiable = self.candidates[j]
+                        .iter()
+                        .any(|k| dependency.allows(&self.available[*k].version()));
+                    if !satisfiable {
+                        return Err(Rejection {
+                            reason: format!(
+                                "{} {} depends on `{}`, but only {} {} is available",
+                                candidate.name(),
+                                candidate.version(),
+                                dependency,
+                                dependency.mod_name(),
+                                format_versions(self.available, &self.candidates[j])
+                            ),
+                            related: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        for (j, k) in self.selected.iter().enumerate() {
+            let other = &self.available[*k];
+            if let Some(dependency) = other.dependencies.get(candidate.name()) {
+                if !dependency.allows(&candidate.version()) {
+                    return Err(Rejection {
+                        reason: format!(
+                            "{} {} depends on `{}`",
+                            other.name(),
+                            other.version(),
+                            dependency
+                        ),
+                        related: Some(j),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn explain(&self, i: usize, rejected: &[Rejection]) -> Explanation {
+        let mut lines = vec![format!("No version of {} can be loaded:", self.names[i])];
+        let mut related = vec![];
+
+        for rejection in rejected {
+            lines.push(format!("  - {}", rejection.reason));
+            if let Some(j) = rejection.related {
+                if !related.contains(&j) {
+                    related.push(j);
+                }
+            }
+        }
+
+        for j in related {
+            let selected = &self.available[self.selected[j]];
+            if self.rejected[j].is_empty() {
+                lines.push(format!(
+                    "  {} {} is selected, because it is the newest available version",
+                    selected.name(),
+                    selected.version()
+                ));
+            }
+            else {
+                lines.push(format!(
+                    "  {} {} is selected, because newer versions were rejected:",
+                    selected.name(),
+                    selected.version()
+                ));
+                for rejection in &self.rejected[j] {
+                    lines.push(format!("    - {}", rejection.reason));
+                }
+            }
+        }
+
+        Explanation { lines }
+    }
+}
+
+/// Sorts the selected mods by dependency depth, then by name.
+fn load_order(available: &[Mod], selected: &[usize]) -> Result<Vec<usize>, DependencyError> {
+    let by_name = selected
+        .iter()
+        .map(|k| (available[*k].name(), *k))
+        .collect::<HashMap<_, _>>();
+
+    let mut depths = HashMap::new();
+    let mut chain = vec![];
+    for k in selected {
+        dependency_depth(*k, available, &by_name, &mut depths, &mut chain)?;
+    }
+
+    let mut order = selected.to_vec();
+    order.sort_by(|a, b| {
+        depths[a]
+            .cmp(&depths[b])
+            .then_with(|| available[*a].name().cmp(available[*b].name()))
+    });
+
+    Ok(order)
+}
+
+fn dependency_depth<'a>(
+    k: usize,
+    available: &'a [Mod],
+    by_name: &HashMap<&str, usize>,
+    depths: &mut HashMap<usize, usize>,
+    chain: &mut Vec<(usize, &'a Dependency)>,
+) -> Result<usize, DependencyError> {
+    if let Some(depth) = depths.get(&k) {
+        return Ok(*depth);
+    }
+
+    if let Some(i) = chain.iter().position(|(other, _)| *other == k) {
+        let cycle = chain[i..]
+            .iter()
+            .map(|(k, dependency)| (available[*k].name().to_owned(), (*dependency).clone()))
+            .collect();
+        return Err(DependencyError::Cycle(cycle));
+    }
+
+    let mut depth = 0;
+    for dependency in available[k].dependencies() {
+        if !dependency.prefix().affects_load_order() {
+            continue;
+        }
+        let Some(other) = by_name.get(dependency.mod_name())
+        else {
+            continue;
+        };
+
+        chain.push((k, dependency));
+        depth = depth.max(dependency_depth(*other, available, by_name, depths, chain)? + 1);
+        chain.pop();
+    }
+
+    depths.insert(k, depth);
+
+    Ok(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        files::ModFiles,
+        InfoJson,
+    };
+
+    fn test_mod(name: &str, version: &str, dependencies: &[&str]) -> Mod {
+        Mod {
+            info: InfoJson {
+                name: name.to_owned(),
+                version: version.to_owned(),
+                title: name.to_owned(),
+                author: String::new(),
+                contact: None,
+                homepage: None,
+                description: None,
+                factorio_version: None,
+                dependencies: dependencies.iter().map(|s| (*s).to_owned()).collect(),
+            },
+            version: version.parse().unwrap(),
+            factorio_version: Version::new(1, 1, 0),
+            dependencies: dependencies
+                .iter()
+                .map(|s| {
+                    let dependency: Dependency = s.parse().unwrap();
+                    (dependency.mod_name().to_owned(), dependency)
+                })
+                .collect(),
+            files: ModFiles::Direcory {
+                path: PathBuf::new(),
+            },
+        }
+    }
+
+    fn names_and_versions(mods: &[Mod]) -> Vec<String> {
+        mods.iter()
+            .map(|fmod| format!("{} {}", fmod.name(), fmod.version()))
+            .collect()
+    }
+
+    #[test]
+    fn it_sorts_by_depth_then_name() {
+        let mods = vec![
+            test_mod("zeta", "1.0.0", &["base"]),
+            test_mod("alpha", "1.0.0", &["zeta", "? beta"]),
+            test_mod("beta", "1.0.0", &["base"]),
+            test_mod("gamma", "1.0.0", &["base", "~ alpha"]),
+            test_mod("base", "1.1.0", &[]),
+        ];
+
+        let mods = resolve(mods, None).unwrap();
+
+        assert_eq!(
+            names_and_versions(&mods),
+            [
+                "base 1.1.0",
+                "beta 1.0.0",
+                "gamma 1.0.0",
+                "zeta 1.0.0",
+                "alpha 1.0.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_picks_the_newest_satisfying_version() {
+        let mods = vec![
+            test_mod("base", "1.1.0", &[]),
+            test_mod("foo", "2.0.0", &["base >= 2.0"]),
+            test_mod("foo", "1.5.0", &["base >= 1.1"]),
+            test_mod("foo", "1.0.0", &["base >= 1.0"]),
+            test_mod("bar", "1.0.0", &["foo < 1.5"]),
+            test_mod("disabled", "1.0.0", &["base"]),
+        ];
+        let mut mod_list = ModList::default();
+        mod_list.set_enabled("disabled", false);
+
+        let mods = resolve(mods, Some(&mod_list)).unwrap();
+
+        assert_eq!(
+            names_and_versions(&mods),
+            ["base 1.1.0", "foo 1.0.0", "bar 1.0.0"]
+        );
+    }
+
+    #[test]
+    fn it_explains_unsatisfiable_dependencies() {
+        let mods = vec![
+            test_mod("base", "1.1.0", &[]),
+            test_mod("foo", "1.0.0", &["base >= 2.0"]),
+            test_mod("bar", "1.0.0", &["missing"]),
+        ];
+
+        let Err(Error::Dependency(DependencyError::Unsatisfiable(explanation))) =
+            resolve(mods, None)
+        else {
+            panic!("expected unsatisfiable dependencies");
+        };
+
+        assert_eq!(
+            explanation.lines,
+            [
+                "No version of bar can be loaded:",
+                "  - bar 1.0.0 depends on `missing`, but missing is not installed"
+            ]
+        );
+    }
+}
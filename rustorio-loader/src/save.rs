@@ -0,0 +1,445 @@
+//! Reading the mods and startup settings a save game was made with.
+//!
+//! A save is a zip file with a directory containing the map. The map starts
+//! with a header, which lists the map version, the active mods with their
+//! versions and the startup settings. Since Factorio 1.1 the map is split into
+//! zlib compressed chunks `level.dat0`, `level.dat1`, etc. Older saves have an
+//! uncompressed `level.dat`. `level-init.dat` holds the map as it was
+//! created, and is only used if neither exists.
+//!
+//! Only the header format of Factorio 0.18 and later is supported.
+
+use std::{
+    fs::File,
+    io::{
+        BufReader,
+        Read,
+        Seek,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use byteorder::{
+    LittleEndian,
+    ReadBytesExt,
+};
+use flate2::read::ZlibDecoder;
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use crate::{
+    proptree::{
+        Deserializer,
+        Value as PropertyTree,
+    },
+    resolver::{
+        ModList,
+        ModListEntry,
+    },
+    Builder,
+    Error,
+    Loader,
+    ModSettings,
+    Version,
+};
+
+/// The map files, in the order in which they're looked for.
+const MAP_FILES: &[&str] = &["level.dat0", "level.dat", "level-init.dat"];
+
+/// A mod that was active when the game was saved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveMod {
+    pub name: String,
+
+    pub version: Version,
+
+    /// The checksum the game computed over the mod's files.
+    pub crc: u32,
+}
+
+/// The header of a save's map.
+#[derive(Clone, Debug)]
+pub struct Save {
+    /// The version of the game that wrote the map.
+    pub version: Version,
+
+    pub build: u16,
+
+    pub campaign: String,
+
+    pub level_name: String,
+
+    /// The mod providing the scenario, usually `base`.
+    pub base_mod: String,
+
+    /// The active mods, including `base`, in the order the game loaded them.
+    pub mods: Vec<SaveMod>,
+
+    /// The startup settings. Runtime settings are stored elsewhere in the map
+    /// and are not read.
+    pub settings: ModSettings,
+}
+
+impl Save {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        log::info!("Reading save: {}", path.display());
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads the header from a save's zip file.
+    pub fn read<R: Read + Seek>(reader: R) -> Result<Self, Error> {
+        let mut zip = ZipArchive::new(reader)?;
+
+        let map_file = MAP_FILES
+            .iter()
+            .find_map(|map_file| {
+                zip.file_names()
+                    .find(|name| {
+                        Path::new(name).file_name().and_then(|s| s.to_str()) == Some(*map_file)
+                    })
+                    .map(ToOwned::to_owned)
+            })
+            .ok_or_else(|| Error::FileNotFound(PathBuf::from(MAP_FILES[0])))?;
+        log::debug!("Reading map header from {}", map_file);
+
+        let mut data = vec![];
+        zip.by_name(&map_file)?.read_to_end(&mut data)?;
+
+        // A zlib stream starts with 0x78. The header starts with the major
+        // version, which is never that.
+        if data.first() == Some(&0x78) {
+            let mut decompressed = vec![];
+            ZlibDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+            data = decompressed;
+        }
+
+        Self::read_header(&data)
+    }
+
+    /// Parses the header at the start of a map.
+    pub fn read_header(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = HeaderReader { input: data };
+
+        let version = Version::new(reader.read_u16()?, reader.read_u16()?, reader.read_u16()?);
+        let build = reader.read_u16()?;
+        if version < Version::new(0, 18, 0) {
+            return Err(Error::InvalidSave(format!(
+                "Unsupported map version: {}",
+                version
+            )));
+        }
+        let _ = reader.read_u8()?;
+
+        let campaign = reader.read_string()?;
+        let level_name = reader.read_string()?;
+        let base_mod = reader.read_string()?;
+
+        // Difficulty, whether the game is finished and the player won.
+        reader.skip(3)?;
+        let _next_level = reader.read_string()?;
+        // Whether the game can be continued, is continued after it was
+        // finished, a replay is saved and non-admins may use debug options.
+        reader.skip(4)?;
+        // The version the map was loaded from and the allowed commands.
+        let _loaded_from = reader.read_version()?;
+        let _loaded_from_build = reader.read_u16()?;
+        let _allowed_commands = reader.read_u8()?;
+
+        let num_mods = reader.read_optimized_u32()?;
+        let mut mods = Vec::with_capacity(num_mods.min(1024) as usize);
+        for _ in 0..num_mods {
+            mods.push(SaveMod {
+                name: reader.read_string()?,
+                version: reader.read_version()?,
+                crc: reader.read_u32()?,
+            });
+        }
+
+        // Depending on the version, the settings are preceded by a 32 bit
+        // value. A property tree always starts with a dictionary here.
+        if reader.input.first() != Some(&5) {
+            reader.skip(4)?;
+        }
+        let mut deserializer = Deserializer::from_slice(reader.input);
+        let startup = PropertyTree::deserialize(&mut deserializer)?;
+        let tree =
+            PropertyTree::Dictionary([("startup".to_owned(), startup)].into_iter().collect());
+        let settings = ModSettings::from_property_tree(version, build, &tree)?;
+
+        Ok(Self {
+            version,
+            build,
+            campaign,
+            level_name,
+            base_mod,
+            mods,
+            settings,
+        })
+    }
+
+    pub fn get_mod(&self, name: &str) -> Option<&SaveMod> {
+        self.mods.iter().find(|fmod| fmod.name == name)
+    }
+
+    /// A mod list that enables exactly the save's mods, pinned to their
+    /// versions.
+    pub fn mod_list(&self) -> ModList {
+        ModList {
+            mods: self
+                .mods
+                .iter()
+                .map(|fmod| {
+                    ModListEntry {
+                        name: fmod.name.clone(),
+                        enabled: true,
+                        version: Some(fmod.version.to_string()),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The save's mods that were not added to the builder, or only in other
+    /// versions.
+    pub fn missing_mods(&self, builder: &Builder) -> Vec<&SaveMod> {
+        self.mods
+            .iter()
+            .filter(|fmod| {
+                !builder
+                    .mods
+                    .iter()
+                    .any(|other| other.name() == fmod.name && other.version() == fmod.version)
+            })
+            .collect()
+    }
+
+    /// Configures the builder to load the save's mods with its startup
+    /// settings. All other mods are disabled. Runtime settings the builder
+    /// already has are kept.
+    ///
+    /// Returns the mods that are missing, see [`Save::missing_mods`].
+    pub fn apply<'a>(&'a self, builder: &mut Builder) -> Vec<&'a SaveMod> {
+        let missing = self.missing_mods(builder);
+
+        let mut mod_list = self.mod_list();
+        for fmod in &builder.mods {
+            if self.get_mod(fmod.name()).is_none() {
+                mod_list.set_enabled(fmod.name(), false);
+            }
+        }
+        builder.set_mod_list(mod_list);
+
+        let mut settings = builder
+            .settings
+            .take()
+            .unwrap_or_else(|| ModSettings::new(self.version));
+        settings.startup = self.settings.startup.clone();
+        builder.set_mod_settings(settings);
+
+        missing
+    }
+
+    /// Creates a loader for the save's mods from the mods added to the
+    /// builder. Fails if any of them are missing.
+    pub fn loader(&self, mut builder: Builder) -> Result<Loader, Error> {
+        let missing = self.apply(&mut builder);
+        if !missing.is_empty() {
+            return Err(Error::MissingMods(
+                missing
+                    .into_iter()
+                    .map(|fmod| format!("{} {}", fmod.name, fmod.version))
+                    .collect(),
+            ));
+        }
+        builder.finish()
+    }
+}
+
+struct HeaderReader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> HeaderReader<'a> {
+    fn skip(&mut self, n: usize) -> Result<(), Error> {
+        self.read_bytes(n)?;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.input.len() {
+            return Err(Error::InvalidSave(
+                "Unexpected end of map header".to_owned(),
+            ));
+        }
+        let (data, rest) = self.input.split_at(n);
+        self.input = rest;
+        Ok(data)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        Ok(self.read_bytes(2)?.read_u16::<LittleEndian>()?)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(self.read_bytes(4)?.read_u32::<LittleEndian>()?)
+    }
+
+    /// A single byte, or `0xff` followed by a `u16`.
+    fn read_optimized_u16(&mut self) -> Result<u16, Error> {
+        match self.read_u8()? {
+            0xff => self.read_u16(),
+            b => Ok(b.into()),
+        }
+    }
+
+    /// A single byte, or `0xff` followed by a `u32`.
+    fn read_optimized_u32(&mut self) -> Result<u32, Error> {
+        match self.read_u8()? {
+            0xff => self.read_u32(),
+            b => Ok(b.into()),
+        }
+    }
+
+    fn read_version(&mut self) -> Result<Version, Error> {
+        Ok(Version::new(
+            self.read_optimized_u16()?,
+            self.read_optimized_u16()?,
+            self.read_optimized_u16()?,
+        ))
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let n = self.read_optimized_u32()? as usize;
+        let data = self.read_bytes(n)?;
+        String::from_utf8(data.to_vec())
+            .map_err(|_| Error::InvalidSave("Invalid UTF-8 in string".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{
+        Cursor,
+        Write,
+    };
+
+    use byteorder::WriteBytesExt;
+    use flate2::{
+        write::ZlibEncoder,
+        Compression,
+    };
+    use zip::ZipWriter;
+
+    use super::*;
+    use crate::{
+        files::ModFiles,
+        settings::{
+            SettingType,
+            SettingValue,
+        },
+    };
+
+    fn write_string(buf: &mut Vec<u8>, s: &str) {
+        buf.push(s.len() as u8);
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn header() -> Vec<u8> {
+        let mut buf = vec![];
+        for n in [1, 1, 104, 0] {
+            buf.write_u16::<LittleEndian>(n).unwrap();
+        }
+        buf.push(0);
+        write_string(&mut buf, "");
+        write_string(&mut buf, "freeplay");
+        write_string(&mut buf, "base");
+        buf.extend_from_slice(&[0, 0, 0]);
+        write_string(&mut buf, "");
+        buf.extend_from_slice(&[0, 0, 0, 0]);
+        buf.extend_from_slice(&[1, 1, 104]);
+        buf.write_u16::<LittleEndian>(62000).unwrap();
+        buf.push(1);
+
+        buf.push(2);
+        for (name, patch) in [("base", 104), ("foo", 3)] {
+            write_string(&mut buf, name);
+            buf.extend_from_slice(&[1, 1, patch]);
+            buf.write_u32::<LittleEndian>(0x1234).unwrap();
+        }
+
+        buf.write_u32::<LittleEndian>(0).unwrap();
+        let setting = PropertyTree::Dictionary(
+            [("value".to_owned(), PropertyTree::Bool(true))]
+                .into_iter()
+                .collect(),
+        );
+        let startup =
+            PropertyTree::Dictionary([("foo-setting".to_owned(), setting)].into_iter().collect());
+        buf.extend(crate::proptree::to_vec(&startup).unwrap());
+        buf
+    }
+
+    fn save_zip() -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&header()).unwrap();
+        let level = encoder.finish().unwrap();
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("my-save/level.dat0", Default::default())
+            .unwrap();
+        zip.write_all(&level).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn mod_files(name: &str, version: &str) -> ModFiles {
+        let info = format!(
+            r#"{{"name": "{}", "version": "{}", "title": "", "author": ""}}"#,
+            name, version
+        );
+        ModFiles::in_memory([("info.json", info.into_bytes())])
+    }
+
+    #[test]
+    fn it_reads_mods_and_startup_settings() {
+        let save = Save::read(Cursor::new(save_zip())).unwrap();
+
+        assert_eq!(save.version, Version::new(1, 1, 104));
+        assert_eq!(save.level_name, "freeplay");
+        assert_eq!(
+            save.mods
+                .iter()
+                .map(|fmod| (fmod.name.as_str(), fmod.version))
+                .collect::<Vec<_>>(),
+            [
+                ("base", Version::new(1, 1, 104)),
+                ("foo", Version::new(1, 1, 3))
+            ]
+        );
+        assert!(matches!(
+            save.settings.get(SettingType::Startup, "foo-setting"),
+            Some(SettingValue::Bool(true))
+        ));
+
+        let mut builder = Builder::with_core_files(ModFiles::in_memory([("core.lua", "")]));
+        builder.add_mod_files(mod_files("base", "1.1.104")).unwrap();
+        builder.add_mod_files(mod_files("foo", "1.1.2")).unwrap();
+        builder.add_mod_files(mod_files("bar", "0.1.0")).unwrap();
+
+        let missing = save.apply(&mut builder);
+        assert_eq!(missing, [save.get_mod("foo").unwrap()]);
+        let mod_list = builder.mod_list.as_ref().unwrap();
+        assert!(!mod_list.is_enabled("bar"));
+        assert_eq!(
+            mod_list.get("foo").unwrap().version.as_deref(),
+            Some("1.1.3")
+        );
+    }
+}
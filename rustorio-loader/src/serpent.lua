@@ -0,0 +1,192 @@
+-- A serializer compatible with the subset of serpent (https://github.com/pkulchenko/serpent)
+-- that is used by mods. Like the game's version, comments with table addresses are
+-- omitted.
+
+local serpent = { _NAME = "serpent", _VERSION = "0.302" }
+
+local keywords = {}
+for _, k in ipairs({
+  "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if",
+  "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+}) do
+  keywords[k] = true
+end
+
+local function quote(s)
+  return (("%q"):format(s):gsub("\010", "n"):gsub("\026", "\\026"))
+end
+
+local function number(n)
+  if n ~= n then
+    return "0/0"
+  elseif n == math.huge then
+    return "math.huge"
+  elseif n == -math.huge then
+    return "-math.huge"
+  elseif n == math.floor(n) and math.abs(n) < 2^53 then
+    return ("%d"):format(n)
+  end
+  for precision = 15, 16 do
+    local s = ("%." .. precision .. "g"):format(n)
+    if tonumber(s) == n then
+      return s
+    end
+  end
+  return ("%.17g"):format(n)
+end
+
+-- Numbers first, then strings in natural order, then everything else.
+local type_order = { number = 1, string = 2 }
+
+local function padded(k)
+  return (tostring(k):gsub("%d+", function(d)
+    return #d < 12 and ("0"):rep(12 - #d) .. d or d
+  end))
+end
+
+local function sort_keys(keys)
+  table.sort(keys, function(a, b)
+    local ta, tb = type_order[type(a)] or 3, type_order[type(b)] or 3
+    if ta ~= tb then
+      return ta < tb
+    elseif ta == 1 then
+      return a < b
+    end
+    return padded(a) < padded(b)
+  end)
+end
+
+local function is_index(k, n)
+  return type(k) == "number" and k >= 1 and k <= n and k == math.floor(k)
+end
+
+local function serialize(value, opts)
+  local indent = opts.indent
+  local eq = opts.compact and "=" or " = "
+  local sep = opts.compact and "," or ", "
+  local maxlevel = opts.maxlevel
+  local maxnum = opts.maxnum
+  local keyignore = opts.keyignore
+  local valtypeignore = opts.valtypeignore
+  local path = {}
+
+  local val
+
+  local function key(k, level)
+    if type(k) == "string" and k:match("^[%a_][%w_]*$") and not keywords[k] then
+      return k
+    end
+    return "[" .. val(k, level) .. "]"
+  end
+
+  function val(v, level)
+    local t = type(v)
+    if t == "string" then
+      return quote(v)
+    elseif t == "number" then
+      return number(v)
+    elseif t == "boolean" or t == "nil" then
+      return tostring(v)
+    elseif t == "function" then
+      return "function() --[[..skipped..]] end"
+    elseif t ~= "table" then
+      return "nil --[[" .. t .. "]]"
+    elseif path[v] then
+      return "nil --[[ref]]"
+    elseif maxlevel and level > maxlevel then
+      return "{} --[[maxlvl]]"
+    end
+
+    path[v] = true
+
+    local items = {}
+    local n = rawlen(v)
+    for i = 1, n do
+      if maxnum and #items >= maxnum then
+        break
+      end
+      items[#items + 1] = val(rawget(v, i), level + 1)
+    end
+
+    local keys = {}
+    for k in next, v do
+      if not is_index(k, n) then
+        keys[#keys + 1] = k
+      end
+    end
+    if opts.sortkeys ~= false then
+      sort_keys(keys)
+    end
+    for _, k in ipairs(keys) do
+      if maxnum and #items >= maxnum then
+        break
+      end
+      local x = rawget(v, k)
+      if not (keyignore and keyignore[k]) and not (valtypeignore and valtypeignore[type(x)]) then
+        items[#items + 1] = key(k, level + 1) .. eq .. val(x, level + 1)
+      end
+    end
+
+    path[v] = nil
+
+    if #items == 0 then
+      return "{}"
+    elseif indent then
+      local pad = indent:rep(level)
+      return "{\n" .. pad .. table.concat(items, ",\n" .. pad) .. "\n" .. indent:rep(level - 1) .. "}"
+    end
+    return "{" .. table.concat(items, sep) .. "}"
+  end
+
+  local s = val(value, 1)
+  if opts.name then
+    return "do local " .. opts.name .. " = " .. s .. "; return " .. opts.name .. "; end"
+  end
+  return s
+end
+
+local function merge(defaults, opts)
+  local merged = {}
+  for k, v in pairs(defaults) do
+    merged[k] = v
+  end
+  for k, v in pairs(opts or {}) do
+    merged[k] = v
+  end
+  return merged
+end
+
+function serpent.serialize(value, opts)
+  return serialize(value, opts or {})
+end
+
+function serpent.line(value, opts)
+  return serialize(value, merge({ sortkeys = true, comment = false }, opts))
+end
+
+function serpent.block(value, opts)
+  return serialize(value, merge({ indent = "  ", sortkeys = true, comment = false }, opts))
+end
+
+function serpent.dump(value, opts)
+  return serialize(value, merge({ name = "_", compact = true, sparse = true }, opts))
+end
+
+function serpent.load(data, opts)
+  opts = opts or {}
+  local env = opts.safe == false and _ENV or { math = { huge = math.huge } }
+  local f, err = load("return " .. data, "serpent", "t", env)
+  if not f then
+    f, err = load(data, "serpent", "t", env)
+  end
+  if not f then
+    return nil, err
+  end
+  local ok, result = pcall(f)
+  if not ok then
+    return nil, result
+  end
+  return true, result
+end
+
+return serpent
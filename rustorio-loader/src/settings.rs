@@ -0,0 +1,791 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+        Read,
+        Write,
+    },
+    path::Path,
+};
+
+use byteorder::{
+    LittleEndian,
+    ReadBytesExt,
+    WriteBytesExt,
+};
+use mlua::{
+    IntoLua,
+    Lua,
+    Table,
+    Value,
+};
+use rustorio_lua_api::{
+    to_option,
+    to_result,
+    Error as LuaApiError,
+    FromLuaTable,
+    FromLuaValue,
+};
+
+use crate::{
+    proptree::Value as PropertyTree,
+    Error,
+    Version,
+};
+
+/// The prototype types that are collected from `data.raw` after the settings
+/// stage.
+pub const SETTING_TYPES: &[&str] = &[
+    "bool-setting",
+    "int-setting",
+    "double-setting",
+    "string-setting",
+    "color-setting",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SettingType {
+    Startup,
+    RuntimeGlobal,
+    RuntimePerUser,
+}
+
+impl SettingType {
+    pub const ALL: [SettingType; 3] = [
+        SettingType::Startup,
+        SettingType::RuntimeGlobal,
+        SettingType::RuntimePerUser,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "startup" => Some(Self::Startup),
+            "runtime-global" => Some(Self::RuntimeGlobal),
+            "runtime-per-user" => Some(Self::RuntimePerUser),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettingType::Startup => "startup",
+            SettingType::RuntimeGlobal => "runtime-global",
+            SettingType::RuntimePerUser => "runtime-per-user",
+        }
+    }
+}
+
+impl FromLuaValue for SettingType {
+    fn from_lua_value(value: Value) -> Result<Self, LuaApiError> {
+        let s = String::from_lua_value(value)?;
+        Self::parse(&s).ok_or_else(|| LuaApiError::other(format!("Invalid setting type: {}", s)))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Color {
+    pub fn new(r: f64, g: f64, b: f64, a: f64) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl FromLuaTable for Color {
+    fn from_lua_table(table: Table) -> Result<Self, LuaApiError> {
+        let r = to_option::<f64>(table.get::<_, Value>(1)?)?;
+        let (mut c, a) = if let Some(r) = r {
+            let g = to_option(table.get::<_, Value>(2)?)?.unwrap_or_default();
+            let b = to_option(table.get::<_, Value>(3)?)?.unwrap_or_default();
+            let a = to_option::<f64>(table.get::<_, Value>(4)?)?;
+            (Color::new(r, g, b, 1.), a)
+        }
+        else {
+            let r = to_option(table.get::<_, Value>("r")?)?.unwrap_or_default();
+            let g = to_option(table.get::<_, Value>("g")?)?.unwrap_or_default();
+            let b = to_option(table.get::<_, Value>("b")?)?.unwrap_or_default();
+            let a = to_option::<f64>(table.get::<_, Value>("a")?)?;
+            (Color::new(r, g, b, 1.), a)
+        };
+
+        // If any component is greater than 1, all of them are in the range 0-255.
+        // A missing alpha component means fully opaque.
+        if c.r > 1. || c.g > 1. || c.b > 1. || a.unwrap_or(0.) > 1. {
+            c.r /= 255.;
+            c.g /= 255.;
+            c.b /= 255.;
+            c.a = a.map_or(1., |a| a / 255.);
+        }
+        else if let Some(a) = a {
+            c.a = a;
+        }
+
+        Ok(c)
+    }
+}
+
+impl<'lua> IntoLua<'lua> for Color {
+    fn into_lua(self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("r", self.r)?;
+        table.set("g", self.g)?;
+        table.set("b", self.b)?;
+        table.set("a", self.a)?;
+        Ok(Value::Table(table))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingValue {
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    Color(Color),
+}
+
+impl SettingValue {
+    /// Converts a value from a `mod-settings.dat` property tree. Returns `None`
+    /// if the value can't be a setting value.
+    pub fn from_property_tree(value: &PropertyTree) -> Option<Self> {
+        match value {
+            PropertyTree::Bool(x) => Some(SettingValue::Bool(*x)),
+            PropertyTree::Number(x) => Some(SettingValue::Double(*x)),
+            PropertyTree::SignedInteger(x) => Some(SettingValue::Int(*x)),
+            PropertyTree::UnsignedInteger(x) => Some(SettingValue::Int((*x).try_into().ok()?)),
+            PropertyTree::String(x) => Some(SettingValue::String(x.clone())),
+            PropertyTree::Dictionary(x) => {
+                let component = |name: &str| {
+                    match x.get(name) {
+                        Some(PropertyTree::Number(x)) => Some(*x),
+                        _ => None,
+                    }
+                };
+                Some(SettingValue::Color(Color::new(
+                    component("r")?,
+                    component("g")?,
+                    component("b")?,
+                    component("a").unwrap_or(1.),
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts the value into a property tree. Before 2.0 the property tree
+    /// had no integer type, so integers are stored as numbers then.
+    pub fn to_property_tree(&self, version: Version) -> PropertyTree {
+        match self {
+            SettingValue::Bool(x) => PropertyTree::Bool(*x),
+            SettingValue::Int(x) if version < Version::new(2, 0, 0) => {
+                PropertyTree::Number(*x as f64)
+            }
+            SettingValue::Int(x) => PropertyTree::SignedInteger(*x),
+            SettingValue::Double(x) => PropertyTree::Number(*x),
+            SettingValue::String(x) => PropertyTree::String(x.clone()),
+            SettingValue::Color(c) => {
+                PropertyTree::Dictionary(
+                    [("r", c.r), ("g", c.g), ("b", c.b), ("a", c.a)]
+                        .into_iter()
+                        .map(|(k, v)| (k.to_owned(), PropertyTree::Number(v)))
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl<'lua> IntoLua<'lua> for SettingValue {
+    fn into_lua(self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
+        match self {
+            SettingValue::Bool(x) => x.into_lua(lua),
+            SettingValue::Int(x) => x.into_lua(lua),
+            SettingValue::Double(x) => x.into_lua(lua),
+            SettingValue::String(x) => x.into_lua(lua),
+            SettingValue::Color(x) => x.into_lua(lua),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SettingPrototype {
+    pub name: String,
+
+    pub setting_type: SettingType,
+
+    pub order: Option<String>,
+
+    pub hidden: bool,
+
+    pub kind: SettingKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum SettingKind {
+    Bool {
+        default_value: bool,
+        forced_value: Option<bool>,
+    },
+    Int {
+        default_value: i64,
+        minimum_value: Option<i64>,
+        maximum_value: Option<i64>,
+        allowed_values: Option<Vec<i64>>,
+    },
+    Double {
+        default_value: f64,
+        minimum_value: Option<f64>,
+        maximum_value: Option<f64>,
+        allowed_values: Option<Vec<f64>>,
+    },
+    String {
+        default_value: String,
+        allow_blank: bool,
+        auto_trim: bool,
+        allowed_values: Option<Vec<String>>,
+    },
+    Color {
+        default_value: Color,
+    },
+}
+
+impl FromLuaTable for SettingPrototype {
+    fn from_lua_table(table: Table) -> Result<Self, LuaApiError> {
+        let r#type: String = to_result(table.get::<_, Value>("type")?, || {
+            LuaApiError::missing_field("type")
+        })?;
+        let default_value = || LuaApiError::missing_field("default_value");
+
+        let kind = match r#type.as_str() {
+            "bool-setting" => {
+                SettingKind::Bool {
+                    default_value: to_result(
+                        table.get::<_, Value>("default_value")?,
+                        default_value,
+                    )?,
+                    forced_value: to_option(table.get::<_, Value>("forced_value")?)?,
+                }
+            }
+            "int-setting" => {
+                SettingKind::Int {
+                    default_value: to_result(
+                        table.get::<_, Value>("default_value")?,
+                        default_value,
+                    )?,
+                    minimum_value: to_option(table.get::<_, Value>("minimum_value")?)?,
+                    maximum_value: to_option(table.get::<_, Value>("maximum_value")?)?,
+                    allowed_values: to_option(table.get::<_, Value>("allowed_values")?)?,
+                }
+            }
+            "double-setting" => {
+                SettingKind::Double {
+                    default_value: to_result(
+                        table.get::<_, Value>("default_value")?,
+                        default_value,
+                    )?,
+                    minimum_value: to_option(table.get::<_, Value>("minimum_value")?)?,
+                    maximum_value: to_option(table.get::<_, Value>("maximum_value")?)?,
+                    allowed_values: to_option(table.get::<_, Value>("allowed_values")?)?,
+                }
+            }
+            "string-setting" => {
+                SettingKind::String {
+                    default_value: to_result(
+                        table.get::<_, Value>("default_value")?,
+                        default_value,
+                    )?,
+                    allow_blank: to_option(table.get::<_, Value>("allow_blank")?)?
+                        .unwrap_or_default(),
+                    auto_trim: to_option(table.get::<_, Value>("auto_trim")?)?.unwrap_or_default(),
+                    allowed_values: to_option(table.get::<_, Value>("allowed_values")?)?,
+                }
+            }
+            "color-setting" => {
+                SettingKind::Color {
+                    default_value: to_result(
+                        table.get::<_, Value>("default_value")?,
+                        default_value,
+                    )?,
+                }
+            }
+            _ => {
+                return Err(LuaApiError::other(format!(
+                    "Not a setting type: {}",
+                    r#type
+                )))
+            }
+        };
+
+        Ok(Self {
+            name: to_result(table.get::<_, Value>("name")?, || {
+                LuaApiError::missing_field("name")
+            })?,
+            setting_type: to_result(table.get::<_, Value>("setting_type")?, || {
+                LuaApiError::missing_field("setting_type")
+            })?,
+            order: to_option(table.get::<_, Value>("order")?)?,
+            hidden: to_option(table.get::<_, Value>("hidden")?)?.unwrap_or_default(),
+            kind,
+        })
+    }
+}
+
+impl SettingPrototype {
+    pub fn default_value(&self) -> SettingValue {
+        match &self.kind {
+            SettingKind::Bool { default_value, .. } => SettingValue::Bool(*default_value),
+            SettingKind::Int { default_value, .. } => SettingValue::Int(*default_value),
+            SettingKind::Double { default_value, .. } => SettingValue::Double(*default_value),
+            SettingKind::String { default_value, .. } => {
+                SettingValue::String(default_value.clone())
+            }
+            SettingKind::Color { default_value } => SettingValue::Color(*default_value),
+        }
+    }
+
+    /// The game stores integers as doubles in older `mod-settings.dat` files
+    /// and a setting may have changed its type between mod versions, so
+    /// numbers are converted to the type of this setting if that's lossless.
+    fn coerce(&self, value: SettingValue) -> SettingValue {
+        match (&self.kind, value) {
+            (SettingKind::Int { .. }, SettingValue::Double(x)) if x.fract() == 0. => {
+                SettingValue::Int(x as i64)
+            }
+            (SettingKind::Double { .. }, SettingValue::Int(x)) => SettingValue::Double(x as f64),
+            (_, value) => value,
+        }
+    }
+
+    /// Determines the effective value of this setting, given the value the
+    /// user chose. Like the game, values that are not valid for this setting
+    /// are replaced with the default value.
+    pub fn resolve(&self, user_value: Option<SettingValue>) -> SettingValue {
+        let user_value = user_value.map(|value| self.coerce(value));
+        match (&self.kind, user_value) {
+            (
+                SettingKind::Bool {
+                    forced_value: Some(forced_value),
+                    ..
+                },
+                _,
+            ) if self.hidden => SettingValue::Bool(*forced_value),
+            (SettingKind::Bool { .. }, Some(SettingValue::Bool(x))) => SettingValue::Bool(x),
+            (
+                SettingKind::Int {
+                    minimum_value,
+                    maximum_value,
+                    allowed_values,
+                    ..
+                },
+                Some(SettingValue::Int(x)),
+            ) => {
+                let valid = allowed_values
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(&x))
+                    && minimum_value.is_none_or(|min| x >= min)
+                    && maximum_value.is_none_or(|max| x <= max);
+                if valid {
+                    SettingValue::Int(x)
+                }
+                else {
+                    self.default_value()
+                }
+            }
+            (
+                SettingKind::Double {
+                    minimum_value,
+                    maximum_value,
+                    allowed_values,
+                    ..
+                },
+                Some(SettingValue::Double(x)),
+            ) => {
+                let valid = allowed_values
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(&x))
+                    && minimum_value.is_none_or(|min| x >= min)
+                    && maximum_value.is_none_or(|max| x <= max);
+                if valid {
+                    SettingValue::Double(x)
+                }
+                else {
+                    self.default_value()
+                }
+            }
+            (
+                SettingKind::String {
+                    allow_blank,
+                    auto_trim,
+                    allowed_values,
+                    ..
+                },
+                Some(SettingValue::String(x)),
+            ) => {
+                let x = if *auto_trim { x.trim().to_owned() } else { x };
+                let valid = allowed_values
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(&x))
+                    && (*allow_blank || !x.is_empty());
+                if valid {
+                    SettingValue::String(x)
+                }
+                else {
+                    self.default_value()
+                }
+            }
+            (SettingKind::Color { .. }, Some(SettingValue::Color(x))) => SettingValue::Color(x),
+            _ => self.default_value(),
+        }
+    }
+}
+
+/// The result of the settings stage: All setting prototypes defined by the
+/// mods, and the effective values of the startup settings.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    pub prototypes: HashMap<String, SettingPrototype>,
+    pub startup: HashMap<String, SettingValue>,
+}
+
+impl Settings {
+    pub fn get(&self, name: &str) -> Option<&SettingPrototype> {
+        self.prototypes.get(name)
+    }
+
+    pub fn startup_value(&self, name: &str) -> Option<&SettingValue> {
+        self.startup.get(name)
+    }
+
+    /// Creates the `settings` table that is available during the data stage.
+    pub fn to_lua_table<'lua>(&self, lua: &'lua Lua) -> Result<Table<'lua>, mlua::Error> {
+        let startup = lua.create_table()?;
+        for (name, value) in &self.startup {
+            let setting = lua.create_table()?;
+            setting.set("value", value.clone())?;
+            startup.set(name.as_str(), setting)?;
+        }
+
+        let settings = lua.create_table()?;
+        settings.set("startup", startup)?;
+
+        Ok(settings)
+    }
+}
+
+/// The contents of a `mod-settings.dat` file: The values the user chose for
+/// the mod settings.
+#[derive(Clone, Debug)]
+pub struct ModSettings {
+    pub version: Version,
+    pub dev_version: u16,
+    pub startup: HashMap<String, SettingValue>,
+    pub runtime_global: HashMap<String, SettingValue>,
+    pub runtime_per_user: HashMap<String, SettingValue>,
+}
+
+impl ModSettings {
+    pub fn new(version: Version) -> Self {
+        Self {
+            version,
+            dev_version: 0,
+            startup: HashMap::new(),
+            runtime_global: HashMap::new(),
+            runtime_per_user: HashMap::new(),
+        }
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let version = Version::new(
+            reader.read_u16::<LittleEndian>()?,
+            reader.read_u16::<LittleEndian>()?,
+            reader.read_u16::<LittleEndian>()?,
+        );
+
+        let dev_version = reader.read_u16::<LittleEndian>()?;
+
+        let _ = reader.read_u8()?;
+
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf)?;
+
+        let tree = crate::proptree::from_slice(&buf)?;
+
+        Self::from_property_tree(version, dev_version, &tree)
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_u16::<LittleEndian>(self.version.major)?;
+        writer.write_u16::<LittleEndian>(self.version.minor)?;
+        writer.write_u16::<LittleEndian>(self.version.patch)?;
+        writer.write_u16::<LittleEndian>(self.dev_version)?;
+        writer.write_u8(0)?;
+
+        let buf = crate::proptree::to_vec(&self.to_property_tree())?;
+        writer.write_all(&buf)?;
+
+        Ok(())
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn from_property_tree(
+        version: Version,
+        dev_version: u16,
+        tree: &PropertyTree,
+    ) -> Result<Self, Error> {
+        let PropertyTree::Dictionary(sections) = tree
+        else {
+            return Err(Error::InvalidModSettings(
+                "Expected a dictionary".to_owned(),
+            ));
+        };
+
+        let mut mod_settings = Self {
+            dev_version,
+            ..Self::new(version)
+        };
+
+        // The game ignores settings it doesn't know, so unknown sections and
+        // invalid entries are skipped instead of failing the whole file.
+        for (section_name, section) in sections {
+            let Some(setting_type) = SettingType::parse(section_name)
+            else {
+                log::warn!("Ignoring unknown mod settings section: {}", section_name);
+                continue;
+            };
+
+            let PropertyTree::Dictionary(settings) = section
+            else {
+                log::warn!(
+                    "Ignoring mod settings section {}: Expected a dictionary",
+                    section_name
+                );
+                continue;
+            };
+
+            for (name, setting) in settings {
+                let value = match setting {
+                    PropertyTree::Dictionary(setting) => setting.get("value"),
+                    _ => None,
+                }
+                .and_then(SettingValue::from_property_tree);
+
+                if let Some(value) = value {
+                    mod_settings.set(setting_type, name, value);
+                }
+                else {
+                    log::warn!("Ignoring invalid value for mod setting {}", name);
+                }
+            }
+        }
+
+        Ok(mod_settings)
+    }
+
+    fn to_property_tree(&self) -> PropertyTree {
+        let sections = SettingType::ALL
+            .into_iter()
+            .map(|setting_type| {
+                let settings = self
+                    .section(setting_type)
+                    .iter()
+                    .map(|(name, value)| {
+                        let setting = [("value".to_owned(), value.to_property_tree(self.version))]
+                            .into_iter()
+                            .collect();
+                        (name.clone(), PropertyTree::Dictionary(setting))
+                    })
+                    .collect();
+                (
+                    setting_type.as_str().to_owned(),
+                    PropertyTree::Dictionary(settings),
+                )
+            })
+            .collect();
+
+        PropertyTree::Dictionary(sections)
+    }
+
+    pub fn section(&self, setting_type: SettingType) -> &HashMap<String, SettingValue> {
+        match setting_type {
+            SettingType::Startup => &self.startup,
+            SettingType::RuntimeGlobal => &self.runtime_global,
+            SettingType::RuntimePerUser => &self.runtime_per_user,
+        }
+    }
+
+    pub fn section_mut(&mut self, setting_type: SettingType) -> &mut HashMap<String, SettingValue> {
+        match setting_type {
+            SettingType::Startup => &mut self.startup,
+            SettingType::RuntimeGlobal => &mut self.runtime_global,
+            SettingType::RuntimePerUser => &mut self.runtime_per_user,
+        }
+    }
+
+    /// Returns the value the user chose for a setting, if any.
+    pub fn get(&self, setting_type: SettingType, name: &str) -> Option<&SettingValue> {
+        self.section(setting_type).get(name)
+    }
+
+    pub fn set(
+        &mut self,
+        setting_type: SettingType,
+        name: impl Into<String>,
+        value: SettingValue,
+    ) -> Option<SettingValue> {
+        self.section_mut(setting_type).insert(name.into(), value)
+    }
+
+    pub fn remove(&mut self, setting_type: SettingType, name: &str) -> Option<SettingValue> {
+        self.section_mut(setting_type).remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_mod_settings() {
+        for version in [Version::new(1, 1, 110), Version::new(2, 0, 7)] {
+            let mut mod_settings = ModSettings::new(version);
+            mod_settings.set(
+                SettingType::Startup,
+                "foo-enabled",
+                SettingValue::Bool(true),
+            );
+            mod_settings.set(SettingType::Startup, "foo-count", SettingValue::Int(42));
+            mod_settings.set(
+                SettingType::RuntimeGlobal,
+                "foo-ratio",
+                SettingValue::Double(0.5),
+            );
+            mod_settings.set(
+                SettingType::RuntimePerUser,
+                "foo-name",
+                SettingValue::String("bar".to_owned()),
+            );
+            mod_settings.set(
+                SettingType::RuntimePerUser,
+                "foo-color",
+                SettingValue::Color(Color::new(1., 0.5, 0., 1.)),
+            );
+
+            let mut buf = vec![];
+            mod_settings.write(&mut buf).unwrap();
+            let read = ModSettings::read(buf.as_slice()).unwrap();
+
+            assert_eq!(read.version, version);
+            assert_eq!(
+                read.get(SettingType::Startup, "foo-enabled"),
+                Some(&SettingValue::Bool(true))
+            );
+            assert_eq!(
+                read.get(SettingType::RuntimeGlobal, "foo-ratio"),
+                Some(&SettingValue::Double(0.5))
+            );
+            assert_eq!(
+                read.get(SettingType::RuntimePerUser, "foo-name"),
+                Some(&SettingValue::String("bar".to_owned()))
+            );
+            assert_eq!(
+                read.get(SettingType::RuntimePerUser, "foo-color"),
+                Some(&SettingValue::Color(Color::new(1., 0.5, 0., 1.)))
+            );
+
+            // Integers are stored as doubles before 2.0 and coerced back when resolved
+            // against the prototype.
+            let count = read
+                .get(SettingType::Startup, "foo-count")
+                .cloned()
+                .unwrap();
+            let prototype = SettingPrototype {
+                name: "foo-count".to_owned(),
+                setting_type: SettingType::Startup,
+                order: None,
+                hidden: false,
+                kind: SettingKind::Int {
+                    default_value: 1,
+                    minimum_value: None,
+                    maximum_value: None,
+                    allowed_values: None,
+                },
+            };
+            assert_eq!(prototype.resolve(Some(count)), SettingValue::Int(42));
+        }
+    }
+
+    #[test]
+    fn it_reads_colors_without_alpha_as_opaque() {
+        let lua = Lua::new();
+        let color = |source: &str| {
+            let table = lua.load(source).eval::<Table>().unwrap();
+            Color::from_lua_table(table).unwrap()
+        };
+
+        assert_eq!(
+            color("{r = 255, g = 102, b = 0}"),
+            Color::new(1., 0.4, 0., 1.)
+        );
+        assert_eq!(color("{255, 102, 0, 51}"), Color::new(1., 0.4, 0., 0.2));
+        assert_eq!(color("{r = 1, g = 0.5}"), Color::new(1., 0.5, 0., 1.));
+        assert_eq!(color("{1, 0.5, 0, 0.5}"), Color::new(1., 0.5, 0., 0.5));
+    }
+
+    #[test]
+    fn it_skips_unknown_mod_settings() {
+        let setting = |value: PropertyTree| {
+            PropertyTree::Dictionary([("value".to_owned(), value)].into_iter().collect())
+        };
+        let tree = PropertyTree::Dictionary(
+            [
+                (
+                    "startup".to_owned(),
+                    PropertyTree::Dictionary(
+                        [
+                            ("foo-enabled".to_owned(), setting(PropertyTree::Bool(true))),
+                            ("foo-list".to_owned(), setting(PropertyTree::List(vec![]))),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                ),
+                (
+                    "runtime-unknown".to_owned(),
+                    PropertyTree::Dictionary(
+                        [("bar".to_owned(), setting(PropertyTree::Bool(true)))]
+                            .into_iter()
+                            .collect(),
+                    ),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let mod_settings =
+            ModSettings::from_property_tree(Version::new(2, 0, 7), 0, &tree).unwrap();
+
+        assert_eq!(
+            mod_settings.get(SettingType::Startup, "foo-enabled"),
+            Some(&SettingValue::Bool(true))
+        );
+        assert_eq!(mod_settings.get(SettingType::Startup, "foo-list"), None);
+    }
+}
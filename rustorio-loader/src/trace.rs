@@ -0,0 +1,382 @@
+use std::{
+    collections::{
+        hash_map::DefaultHasher,
+        BTreeMap,
+        HashMap,
+        HashSet,
+    },
+    ffi::c_void,
+    fmt,
+    hash::{
+        Hash,
+        Hasher,
+    },
+};
+
+use mlua::{
+    Table,
+    Value,
+};
+
+/// Tables nested deeper than this are not looked into when detecting changes.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DataStage {
+    Data,
+    DataUpdates,
+    DataFinalFixes,
+}
+
+impl DataStage {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            DataStage::Data => "data.lua",
+            DataStage::DataUpdates => "data-updates.lua",
+            DataStage::DataFinalFixes => "data-final-fixes.lua",
+        }
+    }
+}
+
+/// The mod and file that ran when a prototype changed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Origin {
+    pub mod_name: String,
+    pub stage: DataStage,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "__{}__/{}", self.mod_name, self.stage.file_name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Created,
+
+    /// The prototype was replaced with a new table, e.g. by calling
+    /// `data:extend` with a prototype of the same name.
+    Replaced,
+
+    Modified {
+        /// The top-level fields that were added, changed or removed.
+        fields: Vec<String>,
+    },
+
+    Removed,
+}
+
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub origin: Origin,
+    pub change: Change,
+}
+
+/// The history of a single prototype during the data stage.
+#[derive(Clone, Debug, Default)]
+pub struct Provenance {
+    pub events: Vec<Event>,
+}
+
+impl Provenance {
+    /// The mod and file that created the prototype as it exists at the end of
+    /// the data stage.
+    pub fn created_by(&self) -> Option<&Origin> {
+        self.events
+            .iter()
+            .rev()
+            .find(|event| matches!(event.change, Change::Created | Change::Replaced))
+            .map(|event| &event.origin)
+    }
+
+    /// All modifications after the prototype was created, with the fields that
+    /// were changed.
+    pub fn modifications(&self) -> impl Iterator<Item = (&Origin, &[String])> {
+        self.events.iter().filter_map(|event| {
+            match &event.change {
+                Change::Modified { fields } => Some((&event.origin, fields.as_slice())),
+                _ => None,
+            }
+        })
+    }
+
+    /// The mods and files that changed a specific field.
+    pub fn modified_field(&self, field: &str) -> impl Iterator<Item = &Origin> + '_ {
+        let field = field.to_owned();
+        self.modifications()
+            .filter(move |(_, fields)| fields.contains(&field))
+            .map(|(origin, _)| origin)
+    }
+
+    pub fn is_removed(&self) -> bool {
+        matches!(
+            self.events.last(),
+            Some(Event {
+                change: Change::Removed,
+                ..
+            })
+        )
+    }
+}
+
+/// Which mods created and modified each prototype during the data stage. See
+/// [`Loader::data_stage_traced`](crate::Loader::data_stage_traced).
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    prototypes: BTreeMap<(String, String), Provenance>,
+}
+
+impl Trace {
+    pub fn get(&self, prototype_type: &str, name: &str) -> Option<&Provenance> {
+        self.prototypes
+            .get(&(prototype_type.to_owned(), name.to_owned()))
+    }
+
+    /// Iterates over `(type, name, provenance)` of all prototypes that existed
+    /// at some point during the data stage.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &Provenance)> {
+        self.prototypes
+            .iter()
+            .map(|((prototype_type, name), provenance)| {
+                (prototype_type.as_str(), name.as_str(), provenance)
+            })
+    }
+}
+
+/// Fingerprints of a prototype's fields.
+#[derive(Debug)]
+struct PrototypeSnapshot {
+    table: *const c_void,
+    fields: HashMap<String, u64>,
+}
+
+/// Records changes to `data.raw` by comparing it after each file that ran
+/// against the previous state. Unlike hooking table writes, this also catches
+/// changes to nested tables, e.g. `table.insert(recipe.ingredients, ...)`.
+#[derive(Debug, Default)]
+pub(crate) struct Tracer {
+    snapshot: HashMap<(String, String), PrototypeSnapshot>,
+    trace: Trace,
+}
+
+impl Tracer {
+    pub fn record(&mut self, data_raw: &Table, origin: Origin) -> Result<(), mlua::Error> {
+        let snapshot = take_snapshot(data_raw)?;
+
+        for (key, new) in &snapshot {
+            let change = match self.snapshot.get(key) {
+                None => Some(Change::Created),
+                Some(old) if old.table != new.table => Some(Change::Replaced),
+                Some(old) => {
+                    let mut fields = new
+                        .fields
+                        .iter()
+                        .filter(|(field, hash)| old.fields.get(*field) != Some(hash))
+                        .map(|(field, _)| field.clone())
+                        .chain(
+                            old.fields
+                                .keys()
+                                .filter(|field| !new.fields.contains_key(*field))
+                                .cloned(),
+                        )
+                        .collect::<Vec<_>>();
+                    fields.sort();
+                    (!fields.is_empty()).then_some(Change::Modified { fields })
+                }
+            };
+
+            if let Some(change) = change {
+                log::trace!("{}: {} {} {:?}", origin, key.0, key.1, change);
+                self.push(key, origin.clone(), change);
+            }
+        }
+
+        let removed = self
+            .snapshot
+            .keys()
+            .filter(|key| !snapshot.contains_key(*key))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in removed {
+            self.push(&key, origin.clone(), Change::Removed);
+        }
+
+        self.snapshot = snapshot;
+
+        Ok(())
+    }
+
+    fn push(&mut self, key: &(String, String), origin: Origin, change: Change) {
+        self.trace
+            .prototypes
+            .entry(key.clone())
+            .or_default()
+            .events
+            .push(Event { origin, change });
+    }
+
+    pub fn finish(self) -> Trace {
+        self.trace
+    }
+}
+
+fn take_snapshot(
+    data_raw: &Table,
+) -> Result<HashMap<(String, String), PrototypeSnapshot>, mlua::Error> {
+    let mut snapshot = HashMap::new();
+
+    for r in data_raw.clone().pairs::<Value, Value>() {
+        let (Value::String(prototype_type), Value::Table(prototypes)) = r?
+        else {
+            continue;
+        };
+        let prototype_type = prototype_type.to_str()?;
+
+        for r in prototypes.pairs::<Value, Value>() {
+            let (Value::String(name), Value::Table(prototype)) = r?
+            else {
+                continue;
+            };
+
+            let mut fields = HashMap::new();
+            for r in prototype.clone().pairs::<Value, Value>() {
+                let (key, value) = r?;
+                if let Some(key) = field_name(&key) {
+                    let mut visited = HashSet::new();
+                    fields.insert(key, fingerprint(&value, &mut visited, 0)?);
+                }
+            }
+
+            snapshot.insert(
+                (prototype_type.to_owned(), name.to_str()?.to_owned()),
+                PrototypeSnapshot {
+                    table: prototype.to_pointer(),
+                    fields,
+                },
+            );
+        }
+    }
+
+    Ok(snapshot)
+}
+
+fn field_name(key: &Value) -> Option<String> {
+    match key {
+        Value::String(s) => Some(s.to_string_lossy().into_owned()),
+        Value::Integer(n) => Some(n.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Hashes a Lua value. Table entries are combined independently of their
+/// iteration order.
+fn fingerprint(
+    value: &Value,
+    visited: &mut HashSet<*const c_void>,
+    depth: usize,
+) -> Result<u64, mlua::Error> {
+    let mut hasher = DefaultHasher::new();
+
+    match value {
+        Value::Nil => 0u8.hash(&mut hasher),
+        Value::Boolean(b) => (1u8, b).hash(&mut hasher),
+        // Integers and floats compare equal in Lua.
+        Value::Integer(n) => (2u8, (*n as f64).to_bits()).hash(&mut hasher),
+        Value::Number(n) => (2u8, n.to_bits()).hash(&mut hasher),
+        Value::String(s) => (3u8, s.as_bytes()).hash(&mut hasher),
+        Value::Table(table) => {
+            4u8.hash(&mut hasher);
+            let pointer = table.to_pointer();
+            if depth < MAX_DEPTH && visited.insert(pointer) {
+                let mut combined = 0u64;
+                for r in table.clone().pairs::<Value, Value>() {
+                    let (key, value) = r?;
+                    let entry = (
+                        fingerprint(&key, visited, depth + 1)?,
+                        fingerprint(&value, visited, depth + 1)?,
+                    );
+                    let mut entry_hasher = DefaultHasher::new();
+                    entry.hash(&mut entry_hasher);
+                    combined = combined.wrapping_add(entry_hasher.finish());
+                }
+                visited.remove(&pointer);
+                combined.hash(&mut hasher);
+            }
+            else {
+                pointer.hash(&mut hasher);
+            }
+        }
+        other => (5u8, other.to_pointer()).hash(&mut hasher),
+    }
+
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use mlua::Lua;
+
+    use super::*;
+
+    fn origin(mod_name: &str, stage: DataStage) -> Origin {
+        Origin {
+            mod_name: mod_name.to_owned(),
+            stage,
+        }
+    }
+
+    #[test]
+    fn it_records_nested_changes() {
+        let lua = Lua::new();
+        let mut tracer = Tracer::default();
+
+        lua.load(
+            r#"
+            data = { raw = { recipe = {
+                ["iron-gear-wheel"] = { name = "iron-gear-wheel", ingredients = {{"iron-plate", 2}} },
+            } } }
+            "#,
+        )
+        .exec()
+        .unwrap();
+        let data_raw = || {
+            lua.globals()
+                .get::<_, Table>("data")
+                .unwrap()
+                .get::<_, Table>("raw")
+                .unwrap()
+        };
+        tracer
+            .record(&data_raw(), origin("base", DataStage::Data))
+            .unwrap();
+
+        lua.load(
+            r#"table.insert(data.raw.recipe["iron-gear-wheel"].ingredients, {"copper-plate", 1})"#,
+        )
+        .exec()
+        .unwrap();
+        tracer
+            .record(&data_raw(), origin("foo", DataStage::DataUpdates))
+            .unwrap();
+
+        // Nothing changes.
+        tracer
+            .record(&data_raw(), origin("bar", DataStage::DataUpdates))
+            .unwrap();
+
+        let trace = tracer.finish();
+        let provenance = trace.get("recipe", "iron-gear-wheel").unwrap();
+
+        assert_eq!(
+            provenance.created_by(),
+            Some(&origin("base", DataStage::Data))
+        );
+        assert_eq!(
+            provenance.modified_field("ingredients").collect::<Vec<_>>(),
+            [&origin("foo", DataStage::DataUpdates)]
+        );
+        assert_eq!(provenance.events.len(), 2);
+    }
+}
@@ -0,0 +1,208 @@
+//! Comparing a translation with the reference locale, usually English, to find
+//! keys that are missing or don't match.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    Locale,
+    Localization,
+    LocalizationElement,
+    PluralizationPattern,
+};
+
+/// The result of [`Coverage::compare`]. Keys are sorted.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    /// The number of keys in the reference.
+    pub total: usize,
+
+    /// Keys of the reference that the translation doesn't have.
+    pub missing: Vec<String>,
+
+    /// Keys that only the translation has. The game ignores them, but they are
+    /// often keys that were renamed.
+    pub extra: Vec<String>,
+
+    pub parameter_mismatches: Vec<ParameterMismatch>,
+
+    pub plural_errors: Vec<PluralError>,
+}
+
+/// A translation that uses other parameters than the reference, e.g. `__2__`
+/// when the reference only has `__1__`.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{key}: expected parameters {expected:?}, found {found:?}")]
+pub struct ParameterMismatch {
+    pub key: String,
+    pub expected: BTreeSet<u32>,
+    pub found: BTreeSet<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{key}: {kind}")]
+pub struct PluralError {
+    pub key: String,
+    pub kind: PluralErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PluralErrorKind {
+    /// The pluralization couldn't be parsed and is shown as text.
+    #[error("malformed pluralization")]
+    Malformed,
+
+    /// No rule matches some counts, which then render as nothing.
+    #[error("pluralization for __{0}__ has no `rest` rule")]
+    MissingRest(u32),
+
+    /// A rule after `rest`, that is never used.
+    #[error("pluralization for __{0}__ has rules after `rest`")]
+    UnreachableRule(u32),
+}
+
+impl Coverage {
+    /// Compares a translation with the reference.
+    pub fn compare(reference: &Locale, translation: &Locale) -> Self {
+        let mut coverage = Self::default();
+
+        for (key, expected) in reference.iter() {
+            coverage.total += 1;
+            let Some(localization) = translation.get(&key)
+            else {
+                coverage.missing.push(key);
+                continue;
+            };
+
+            let expected = expected.parameters();
+            let found = localization.parameters();
+            if expected != found {
+                coverage.parameter_mismatches.push(ParameterMismatch {
+                    key: key.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        for (key, localization) in translation.iter() {
+            if reference.get(&key).is_none() {
+                coverage.extra.push(key.clone());
+            }
+            coverage
+                .plural_errors
+                .extend(localization.plural_errors().into_iter().map(|kind| {
+                    PluralError {
+                        key: key.clone(),
+                        kind,
+                    }
+                }));
+        }
+
+        coverage.missing.sort();
+        coverage.extra.sort();
+        coverage
+            .parameter_mismatches
+            .sort_by(|a, b| a.key.cmp(&b.key));
+        coverage.plural_errors.sort_by(|a, b| a.key.cmp(&b.key));
+
+        coverage
+    }
+
+    /// The number of keys of the reference that are translated.
+    pub fn translated(&self) -> usize {
+        self.total - self.missing.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.parameter_mismatches.is_empty()
+            && self.plural_errors.is_empty()
+    }
+}
+
+impl Localization {
+    /// The parameters that the localization uses, including those that
+    /// pluralizations depend on.
+    pub fn parameters(&self) -> BTreeSet<u32> {
+        let mut parameters = BTreeSet::new();
+        self.collect_parameters(&mut parameters);
+        parameters
+    }
+
+    fn collect_parameters(&self, parameters: &mut BTreeSet<u32>) {
+        for element in &self.elements {
+            match element {
+                LocalizationElement::Parameter(n) => {
+                    parameters.insert(*n);
+                }
+                LocalizationElement::Pluralization { parameter, rules } => {
+                    parameters.insert(*parameter);
+                    for rule in rules {
+                        rule.text.collect_parameters(parameters);
+                    }
+                }
+                LocalizationElement::RichText { value, .. } => value.collect_parameters(parameters),
+                _ => {}
+            }
+        }
+    }
+
+    fn plural_errors(&self) -> Vec<PluralErrorKind> {
+        let mut errors = vec![];
+        for element in &self.elements {
+            match element {
+                LocalizationElement::Text(text) if text.contains("__plural_for_parameter") => {
+                    errors.push(PluralErrorKind::Malformed);
+                }
+                LocalizationElement::Pluralization { parameter, rules } => {
+                    let rest = rules
+                        .iter()
+                        .position(|rule| rule.patterns.contains(&PluralizationPattern::Rest));
+                    match rest {
+                        None => errors.push(PluralErrorKind::MissingRest(*parameter)),
+                        Some(i) if i + 1 < rules.len() => {
+                            errors.push(PluralErrorKind::UnreachableRule(*parameter));
+                        }
+                        Some(_) => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_compares_a_translation() {
+        let reference = Locale::parse(
+            "[item-name]\niron-plate=Iron plate\ncopper-plate=Copper plate\n[gui]\namount=__1__ × __2__\nminutes=__plural_for_parameter_1_{1=minute|rest=minutes}__\n",
+        )
+        .unwrap();
+        let translation = Locale::parse(
+            "[item-name]\niron-plate=Eisenplatte\nsteel-plate=Stahlplatte\n[gui]\namount=__1__ ×\nminutes=__plural_for_parameter_1_{1=Minute|2=Minuten}__ __plural_for_parameter_1_{1=Minute\n",
+        )
+        .unwrap();
+
+        let coverage = Coverage::compare(&reference, &translation);
+        assert_eq!(coverage.total, 4);
+        assert_eq!(coverage.translated(), 3);
+        assert_eq!(coverage.missing, ["item-name.copper-plate"]);
+        assert_eq!(coverage.extra, ["item-name.steel-plate"]);
+        assert_eq!(coverage.parameter_mismatches.len(), 1);
+        assert_eq!(coverage.parameter_mismatches[0].key, "gui.amount");
+        assert_eq!(
+            coverage
+                .plural_errors
+                .iter()
+                .map(|error| error.kind.clone())
+                .collect::<Vec<_>>(),
+            [PluralErrorKind::MissingRest(1), PluralErrorKind::Malformed]
+        );
+    }
+}
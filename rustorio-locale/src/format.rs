@@ -0,0 +1,168 @@
+//! Formatting localizations in the syntax of locale files, such that parsing
+//! the output gives the same localization.
+
+use std::fmt::{
+    self,
+    Display,
+    Formatter,
+};
+
+use crate::{
+    Category,
+    Control,
+    Locale,
+    Localization,
+    LocalizationElement,
+    PluralizationPattern,
+    PluralizationRule,
+};
+
+impl Display for Localization {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for element in &self.elements {
+            write!(f, "{}", element)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for LocalizationElement {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Text(text) => write!(f, "{}", text),
+            Self::Parameter(n) => write!(f, "__{}__", n),
+            Self::Entity(name) => write!(f, "__ENTITY__{}__", name),
+            Self::Item(name) => write!(f, "__ITEM__{}__", name),
+            Self::Tile(name) => write!(f, "__TILE__{}__", name),
+            Self::Fluid(name) => write!(f, "__FLUID__{}__", name),
+            Self::Pluralization { parameter, rules } => {
+                write!(f, "__plural_for_parameter_{}_{{", parameter)?;
+                for (i, rule) in rules.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    write!(f, "{}", rule)?;
+                }
+                write!(f, "}}__")
+            }
+            Self::Control(control) => write!(f, "{}", control),
+            Self::ControlStyleBegin => write!(f, "__CONTROL_STYLE_BEGIN__"),
+            Self::ControlStyleEnd => write!(f, "__CONTROL_STYLE_END__"),
+            Self::RemarkColorBegin => write!(f, "__REMARK_COLOR_BEGIN__"),
+            Self::RemarkColorEnd => write!(f, "__REMARK_COLOR_END__"),
+            Self::RichText { tag, value } => write!(f, "[{}={}]", tag, value),
+            Self::RichTextEnd(tag) => write!(f, "[/{}]", tag),
+        }
+    }
+}
+
+impl Display for Control {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Key(name) => write!(f, "__CONTROL__{}__", name),
+            Self::Modifier(name) => write!(f, "__CONTROL_MODIFIER__{}__", name),
+            Self::Alt { style, name } => write!(f, "__ALT_CONTROL__{}__{}__", style, name),
+            Self::LeftClick => write!(f, "__CONTROL_LEFT_CLICK__"),
+            Self::RightClick => write!(f, "__CONTROL_RIGHT_CLICK__"),
+            Self::KeyShift => write!(f, "__CONTROL_KEY_SHIFT__"),
+            Self::KeyCtrl => write!(f, "__CONTROL_KEY_CTRL__"),
+            Self::AltLeftClick(style) => write!(f, "__ALT_CONTROL_LEFT_CLICK__{}__", style),
+            Self::AltRightClick(style) => write!(f, "__ALT_CONTROL_RIGHT_CLICK__{}__", style),
+        }
+    }
+}
+
+impl Display for PluralizationRule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", pattern)?;
+        }
+        write!(f, "={}", self.text)
+    }
+}
+
+impl Display for PluralizationPattern {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::EndsWith(n) => write!(f, "ends in {}", n),
+            Self::Rest => write!(f, "rest"),
+        }
+    }
+}
+
+impl Display for Category {
+    /// Formats the keys of the category, sorted by name.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut values = self.values.iter().collect::<Vec<_>>();
+        values.sort_by_key(|(key, _)| *key);
+        for (key, localization) in values {
+            writeln!(f, "{}={}", key, localization)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Locale {
+    /// Formats the locale as a locale file, with the categories sorted by name.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.global)?;
+        let mut categories = self.categories.iter().collect::<Vec<_>>();
+        categories.sort_by_key(|(name, _)| *name);
+        for (name, category) in categories {
+            writeln!(f)?;
+            writeln!(f, "[{}]", name)?;
+            write!(f, "{}", category)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_formats_what_it_parses() {
+        let inputs = [
+            "Press __CONTROL__build__ or __ALT_CONTROL__1__build__ with __CONTROL_MODIFIER__build-ghost__ and __CONTROL_LEFT_CLICK__",
+            "__CONTROL_STYLE_BEGIN__Shift__CONTROL_STYLE_END__ __ALT_CONTROL_RIGHT_CLICK__2__ __CONTROL_KEY_SHIFT__",
+            "__REMARK_COLOR_BEGIN__Note:__REMARK_COLOR_END__ [color=red]__1__[/color] [item=__2__] [WIP] __FOO__",
+            "__plural_for_parameter_1_{1=__1__ __ITEM__plate__|ends in 2,ends in 3,4=few|rest=}__",
+        ];
+        for input in inputs {
+            let localization = Localization::parse(input).unwrap();
+            assert!(!localization.elements.is_empty());
+            assert_eq!(localization.to_string(), input);
+            assert_eq!(
+                Localization::parse(&localization.to_string()).unwrap(),
+                localization
+            );
+        }
+
+        let localization = Localization::parse(
+            "__plural_for_parameter__1__{1=one|rest=many}__ __ALT_CONTROL__1__build__ [item=__1__]",
+        )
+        .unwrap();
+        assert!(matches!(
+            localization.elements.as_slice(),
+            [
+                LocalizationElement::Pluralization { parameter: 1, rules },
+                LocalizationElement::Text(_),
+                LocalizationElement::Control(Control::Alt { style: 1, name }),
+                LocalizationElement::Text(_),
+                LocalizationElement::RichText { tag, value },
+            ] if rules.len() == 2
+                && name == "build"
+                && tag == "item"
+                && value.elements == [LocalizationElement::Parameter(1)]
+        ));
+        assert_eq!(
+            localization.to_string(),
+            "__plural_for_parameter_1_{1=one|rest=many}__ __ALT_CONTROL__1__build__ [item=__1__]"
+        );
+    }
+}
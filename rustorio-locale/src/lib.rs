@@ -1,8 +1,10 @@
+pub mod coverage;
+mod format;
 mod parser;
+mod render;
 
 use std::{
     collections::HashMap,
-    path::Path,
     string::FromUtf8Error,
 };
 
@@ -12,6 +14,13 @@ use serde::{
     Serialize,
 };
 
+pub use crate::render::{
+    unknown_key,
+    unresolved_reference,
+    Resolver,
+    MAX_REFERENCE_DEPTH,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
     #[error("{0}")]
@@ -20,42 +29,147 @@ pub enum ParseError {
     Utf8(#[from] FromUtf8Error),
 }
 
-#[derive(Clone, Debug)]
+/// An element of a localization. See the
+/// [wiki](https://wiki.factorio.com/Tutorial:Localisation) for the syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub enum LocalizationElement {
     Text(String),
+
+    /// `__1__`
     Parameter(u32),
+
+    /// `__ENTITY__name__`
     Entity(String),
+
+    /// `__ITEM__name__`
     Item(String),
+
+    /// `__TILE__name__`
     Tile(String),
+
+    /// `__FLUID__name__`
     Fluid(String),
+
+    /// `__plural_for_parameter_1_{1=plate|rest=plates}__`
     Pluralization {
         parameter: u32,
         rules: Vec<PluralizationRule>,
     },
-    // todo
-    Todo,
+
+    /// A key binding, e.g. `__CONTROL__build__`.
+    Control(Control),
+
+    /// `__CONTROL_STYLE_BEGIN__`
+    ControlStyleBegin,
+
+    /// `__CONTROL_STYLE_END__`
+    ControlStyleEnd,
+
+    /// `__REMARK_COLOR_BEGIN__`
+    RemarkColorBegin,
+
+    /// `__REMARK_COLOR_END__`
+    RemarkColorEnd,
+
+    /// A rich text tag, e.g. `[item=iron-plate]` or `[color=red]`. The value
+    /// can contain parameters, e.g. `[item=__1__]`.
+    RichText {
+        tag: String,
+        value: Localization,
+    },
+
+    /// The end of a rich text tag, e.g. `[/color]`.
+    RichTextEnd(String),
+}
+
+/// A key binding in a localization.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// `__CONTROL__name__`, the keys bound to a control.
+    Key(String),
+
+    /// `__CONTROL_MODIFIER__name__`, only the modifier keys of a control.
+    Modifier(String),
+
+    /// `__ALT_CONTROL__1__name__`, the keys bound to a control in an
+    /// alternative style.
+    Alt { style: u32, name: String },
+
+    /// `__CONTROL_LEFT_CLICK__`
+    LeftClick,
+
+    /// `__CONTROL_RIGHT_CLICK__`
+    RightClick,
+
+    /// `__CONTROL_KEY_SHIFT__`
+    KeyShift,
+
+    /// `__CONTROL_KEY_CTRL__`
+    KeyCtrl,
+
+    /// `__ALT_CONTROL_LEFT_CLICK__1__`
+    AltLeftClick(u32),
+
+    /// `__ALT_CONTROL_RIGHT_CLICK__1__`
+    AltRightClick(u32),
 }
 
-#[derive(Clone, Debug)]
+/// A rule of a pluralization, e.g. `1=plate` or `ends in 2,ends in 3=plates`.
+/// The first rule with a matching pattern is used.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PluralizationRule {
-    pub pattern: PluralizationPattern,
-    pub text: String,
+    pub patterns: Vec<PluralizationPattern>,
+    pub text: Localization,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PluralizationPattern {
-    Numbers(Vec<u32>),
-    EndsWith(Vec<u32>),
+    /// `1`
+    Number(u32),
+
+    /// `ends in 1`
+    EndsWith(u32),
+
+    /// `rest`
     Rest,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct Localization {
     pub elements: Vec<LocalizationElement>,
 }
 
+impl Localization {
+    /// Parses a single value, as it appears after the `=` in a locale file.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        match parser::parse_value(input) {
+            Ok((_, localization)) => Ok(localization),
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                Err(ParseError::Nom(nom::error::convert_error(input, e)))
+            }
+            Err(nom::Err::Incomplete(_)) => {
+                unreachable!("complete parsers don't return Incomplete")
+            }
+        }
+    }
+
+    /// Creates a localization, joining adjacent text.
+    pub fn from_elements(elements: impl IntoIterator<Item = LocalizationElement>) -> Self {
+        let mut joined: Vec<LocalizationElement> = vec![];
+        for element in elements {
+            match (joined.last_mut(), element) {
+                (Some(LocalizationElement::Text(text)), LocalizationElement::Text(more)) => {
+                    text.push_str(&more);
+                }
+                (_, element) => joined.push(element),
+            }
+        }
+        Self { elements: joined }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct Category {
@@ -76,21 +190,34 @@ pub struct Locale {
 }
 
 impl Locale {
-    /*pub fn load(files: &ModFiles, language: &str) -> Result<Self, Error> {
-        let mut locale = Self::default();
-        let dir = Path::new("locale").join(language);
-
-        for file_name in files.list_dir(&dir)? {
-            let data = files.read(&file_name)?;
-            let data = String::from_utf8(data).map_err(ParseError::from)?;
-            let data = Self::parse(&data)?;
-            locale.merge_into(data);
+    /// Looks up a key like `item-name.iron-plate`. Keys without a category
+    /// are looked up in the global category.
+    pub fn get(&self, key: &str) -> Option<&Localization> {
+        match key.split_once('.') {
+            Some((category, name)) => self.categories.get(category)?.values.get(name),
+            None => self.global.values.get(key),
         }
+    }
 
-        Ok(locale)
-    }*/
+    /// All keys with their localizations. Keys are `category.name`, or only
+    /// the name for keys in the global category.
+    pub fn iter(&self) -> impl Iterator<Item = (String, &Localization)> {
+        let global = self
+            .global
+            .values
+            .iter()
+            .map(|(name, localization)| (name.clone(), localization));
+        let categories = self.categories.iter().flat_map(|(category, values)| {
+            values
+                .values
+                .iter()
+                .map(move |(name, localization)| (format!("{}.{}", category, name), localization))
+        });
+        global.chain(categories)
+    }
 
     pub fn parse(input: &str) -> Result<Locale, ParseError> {
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
         match parser::parse_locale(input) {
             Ok((_, locale)) => Ok(locale),
             Err(nom::Err::Error(e)) => Err(ParseError::Nom(nom::error::convert_error(input, e))),
@@ -106,3 +233,36 @@ impl Locale {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_a_locale_file() {
+        let locale = Locale::parse(
+            "\u{feff}; comment\n[item-name]\niron-plate=Iron plate\nchest=Chest __1__\r\n\n[entity-description]\nlab=Uses_science__packs __ITEM__iron-plate__ \n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            locale.get("item-name.iron-plate").unwrap().elements.as_slice(),
+            [LocalizationElement::Text(text)] if text == "Iron plate"
+        ));
+        assert!(matches!(
+            locale.get("item-name.chest").unwrap().elements.as_slice(),
+            [
+                LocalizationElement::Text(_),
+                LocalizationElement::Parameter(1)
+            ]
+        ));
+        assert!(matches!(
+            locale.get("entity-description.lab").unwrap().elements.as_slice(),
+            [
+                LocalizationElement::Text(text),
+                LocalizationElement::Item(item),
+                LocalizationElement::Text(_),
+            ] if text == "Uses_science__packs " && item == "iron-plate"
+        ));
+    }
+}
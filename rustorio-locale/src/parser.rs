@@ -1,44 +1,34 @@
 use nom::{
     branch::alt,
     bytes::complete::{
-        escaped_transform,
         is_not,
         tag,
-        take,
+        take_till,
         take_until,
         take_while1,
     },
     character::complete::{
         char,
-        digit1,
         line_ending,
-        multispace0,
-        multispace1,
-        newline,
         none_of,
-        not_line_ending,
-        one_of,
+        space0,
     },
     combinator::{
         all_consuming,
-        cut,
         map,
-        not,
+        map_parser,
         opt,
-        peek,
+        recognize,
         value,
+        verify,
     },
     error::{
-        context,
         ErrorKind,
-        FromExternalError,
         ParseError,
         VerboseError,
     },
     multi::{
         many0,
-        many0_count,
-        separated_list0,
         separated_list1,
     },
     sequence::{
@@ -50,11 +40,11 @@ use nom::{
         tuple,
     },
     IResult,
-    Parser,
 };
 
 use super::{
     Category,
+    Control,
     Locale,
     Localization,
     LocalizationElement,
@@ -68,8 +58,15 @@ fn comment(input: &str) -> Res<()> {
     value((), tuple((alt((char('#'), char(';'))), is_not("\r\n"))))(input)
 }
 
+/// Empty lines and comments.
 fn consume_junk(input: &str) -> Res<()> {
-    value((), many0(alt((value((), line_ending), value((), comment)))))(input)
+    value(
+        (),
+        many0(alt((
+            value((), pair(space0, line_ending)),
+            preceded(space0, comment),
+        ))),
+    )(input)
 }
 
 fn consume_junk_before<'a, U>(
@@ -80,7 +77,13 @@ fn consume_junk_before<'a, U>(
 
 pub(super) fn parse_locale<'a>(input: &'a str) -> Res<'a, Locale> {
     all_consuming(map(
-        tuple((parse_category_body, many0(parse_category))),
+        terminated(
+            tuple((
+                parse_category_body,
+                many0(consume_junk_before(parse_category)),
+            )),
+            pair(consume_junk, space0),
+        ),
         |(global, categories)| {
             Locale {
                 global,
@@ -94,7 +97,7 @@ fn parse_category<'a>(input: &'a str) -> Res<'a, (String, Category)> {
     map(
         tuple((
             map(
-                delimited(char('['), is_not("\r\n"), char(']')),
+                delimited(char('['), is_not("]\r\n"), char(']')),
                 |name: &str| name.to_owned(),
             ),
             parse_category_body,
@@ -106,7 +109,10 @@ fn parse_category<'a>(input: &'a str) -> Res<'a, (String, Category)> {
 fn parse_category_body<'a>(input: &'a str) -> Res<'a, Category> {
     map(
         many0(consume_junk_before(separated_pair(
-            map(is_not("="), |s: &str| s.to_owned()),
+            map(
+                recognize(pair(none_of("[=\r\n"), opt(is_not("=\r\n")))),
+                |s: &str| s.trim().to_owned(),
+            ),
             char('='),
             parse_localization,
         ))),
@@ -118,79 +124,202 @@ fn parse_category_body<'a>(input: &'a str) -> Res<'a, Category> {
     )(input)
 }
 
+/// Parses a single value, e.g. the text of a pluralization rule.
+pub(super) fn parse_value<'a>(input: &'a str) -> Res<'a, Localization> {
+    all_consuming(parse_localization)(input)
+}
+
+/// Parses the value of a key, up to the end of the line.
 fn parse_localization<'a>(input: &'a str) -> Res<'a, Localization> {
     map(
         many0(alt((
             delimited(tag("__"), parse_parameter, tag("__")),
-            map(take_until("__"), |text: &str| {
+            parse_rich_text,
+            map(parse_text, |text: &str| {
+                LocalizationElement::Text(text.to_owned())
+            }),
+            // A `__` or `[` that doesn't start a parameter or tag.
+            map(alt((tag("__"), tag("["))), |text: &str| {
                 LocalizationElement::Text(text.to_owned())
             }),
         ))),
-        |elements| Localization { elements },
+        Localization::from_elements,
+    )(input)
+}
+
+/// Text up to the next `__`, `[` or the end of the line.
+fn parse_text(input: &str) -> Res<'_, &str> {
+    let end = input
+        .char_indices()
+        .find(|(i, c)| matches!(c, '\r' | '\n' | '[') || input[*i..].starts_with("__"))
+        .map_or(input.len(), |(i, _)| i);
+    if end == 0 {
+        return Err(nom::Err::Error(VerboseError::from_error_kind(
+            input,
+            ErrorKind::TakeUntil,
+        )));
+    }
+    Ok((&input[end..], &input[..end]))
+}
+
+/// A name in a parameter, e.g. `build` in `__CONTROL__build__`.
+fn parse_name(input: &str) -> Res<'_, String> {
+    map(
+        verify(take_until("__"), |name: &str| {
+            !name.is_empty() && !name.contains(char::is_whitespace)
+        }),
+        |name: &str| name.to_owned(),
     )(input)
 }
 
 fn parse_parameter<'a>(input: &'a str) -> Res<'a, LocalizationElement> {
     alt((
-        map(nom::character::complete::u32, |param: u32| {
-            LocalizationElement::Parameter(param)
-        }),
+        map(
+            nom::character::complete::u32,
+            LocalizationElement::Parameter,
+        ),
+        parse_pluralization,
+        map(
+            preceded(tag("ENTITY__"), parse_name),
+            LocalizationElement::Entity,
+        ),
+        map(
+            preceded(tag("ITEM__"), parse_name),
+            LocalizationElement::Item,
+        ),
+        map(
+            preceded(tag("TILE__"), parse_name),
+            LocalizationElement::Tile,
+        ),
+        map(
+            preceded(tag("FLUID__"), parse_name),
+            LocalizationElement::Fluid,
+        ),
+        value(
+            LocalizationElement::ControlStyleBegin,
+            tag("CONTROL_STYLE_BEGIN"),
+        ),
+        value(
+            LocalizationElement::ControlStyleEnd,
+            tag("CONTROL_STYLE_END"),
+        ),
+        value(
+            LocalizationElement::RemarkColorBegin,
+            tag("REMARK_COLOR_BEGIN"),
+        ),
+        value(LocalizationElement::RemarkColorEnd, tag("REMARK_COLOR_END")),
+        map(parse_control, LocalizationElement::Control),
+    ))(input)
+}
+
+fn parse_control(input: &str) -> Res<'_, Control> {
+    alt((
+        map(preceded(tag("CONTROL__"), parse_name), Control::Key),
+        map(
+            preceded(tag("CONTROL_MODIFIER__"), parse_name),
+            Control::Modifier,
+        ),
+        value(Control::LeftClick, tag("CONTROL_LEFT_CLICK")),
+        value(Control::RightClick, tag("CONTROL_RIGHT_CLICK")),
+        value(Control::KeyShift, tag("CONTROL_KEY_SHIFT")),
+        value(Control::KeyCtrl, tag("CONTROL_KEY_CTRL")),
         map(
             tuple((
-                tag("__plural_for_parameter_"),
+                tag("ALT_CONTROL__"),
                 nom::character::complete::u32,
-                tag("_{"),
-                separated_list1(
-                    char('|'),
-                    separated_pair(parse_pluralization_pattern, char('='), is_not("|}")),
-                ),
-                tag("}"),
+                tag("__"),
+                parse_name,
             )),
-            |(_, parameter, _, rules, _)| {
-                LocalizationElement::Pluralization {
-                    parameter,
-                    rules: rules
-                        .into_iter()
-                        .map(|(pattern, text)| {
-                            PluralizationRule {
-                                pattern,
-                                text: text.to_owned(),
-                            }
-                        })
-                        .collect(),
-                }
-            },
+            |(_, style, _, name)| Control::Alt { style, name },
         ),
         map(
-            preceded(tag("ENTITY__"), take_until("__")),
-            |param: &str| LocalizationElement::Entity(param.to_owned()),
+            preceded(
+                tag("ALT_CONTROL_LEFT_CLICK__"),
+                nom::character::complete::u32,
+            ),
+            Control::AltLeftClick,
+        ),
+        map(
+            preceded(
+                tag("ALT_CONTROL_RIGHT_CLICK__"),
+                nom::character::complete::u32,
+            ),
+            Control::AltRightClick,
+        ),
+    ))(input)
+}
+
+/// `plural_for_parameter_1_{...}`, or `plural_for_parameter__1__{...}` as
+/// written by newer versions of the game.
+fn parse_pluralization(input: &str) -> Res<'_, LocalizationElement> {
+    map(
+        tuple((
+            tag("plural_for_parameter_"),
+            alt((
+                delimited(char('_'), nom::character::complete::u32, tag("__")),
+                terminated(nom::character::complete::u32, char('_')),
+            )),
+            delimited(
+                char('{'),
+                separated_list1(char('|'), parse_pluralization_rule),
+                char('}'),
+            ),
+        )),
+        |(_, parameter, rules)| LocalizationElement::Pluralization { parameter, rules },
+    )(input)
+}
+
+fn parse_pluralization_rule(input: &str) -> Res<'_, PluralizationRule> {
+    map(
+        separated_pair(
+            separated_list1(char(','), parse_pluralization_pattern),
+            char('='),
+            map_parser(
+                take_till(|c| matches!(c, '|' | '}' | '\r' | '\n')),
+                all_consuming(parse_localization),
+            ),
+        ),
+        |(patterns, text)| PluralizationRule { patterns, text },
+    )(input)
+}
+
+fn parse_pluralization_pattern(input: &str) -> Res<'_, PluralizationPattern> {
+    alt((
+        value(PluralizationPattern::Rest, tag("rest")),
+        map(nom::character::complete::u32, PluralizationPattern::Number),
+        map(
+            preceded(tag("ends in "), nom::character::complete::u32),
+            PluralizationPattern::EndsWith,
         ),
-        map(preceded(tag("ITEM__"), take_until("__")), |param: &str| {
-            LocalizationElement::Item(param.to_owned())
-        }),
-        map(preceded(tag("TILE__"), take_until("__")), |param: &str| {
-            LocalizationElement::Tile(param.to_owned())
-        }),
-        map(preceded(tag("FLUID__"), take_until("__")), |param: &str| {
-            LocalizationElement::Fluid(param.to_owned())
-        }),
-        map(take_until("__"), |_| LocalizationElement::Todo),
     ))(input)
 }
 
-fn parse_pluralization_pattern<'a>(input: &'a str) -> Res<'a, PluralizationPattern> {
+/// A rich text tag like `[item=iron-plate]`, or the end of one like
+/// `[/color]`.
+fn parse_rich_text(input: &str) -> Res<'_, LocalizationElement> {
+    let tag_name = |input| {
+        map(
+            take_while1(|c: char| c.is_ascii_lowercase() || c == '-' || c == '_'),
+            |name: &str| name.to_owned(),
+        )(input)
+    };
+
     alt((
-        map(tag("rest"), |_| PluralizationPattern::Rest),
         map(
-            separated_list1(char(','), nom::character::complete::u32),
-            |values| PluralizationPattern::Numbers(values),
+            delimited(tag("[/"), tag_name, char(']')),
+            LocalizationElement::RichTextEnd,
         ),
         map(
-            separated_list1(
-                char(','),
-                preceded(tag("ends in "), nom::character::complete::u32),
+            delimited(
+                char('['),
+                separated_pair(
+                    tag_name,
+                    char('='),
+                    map_parser(is_not("[]\r\n"), all_consuming(parse_localization)),
+                ),
+                char(']'),
             ),
-            |values| PluralizationPattern::EndsWith(values),
+            |(tag, value)| LocalizationElement::RichText { tag, value },
         ),
     ))(input)
 }
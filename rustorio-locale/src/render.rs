@@ -0,0 +1,193 @@
+use crate::{
+    Control,
+    Locale,
+    Localization,
+    LocalizationElement,
+    PluralizationPattern,
+    PluralizationRule,
+};
+
+/// Renders the parts of a localization that depend on the game: the names of
+/// prototypes and the keys bound to controls.
+///
+/// Closures `Fn(&str, &str) -> String` implement it, with the default key
+/// bindings.
+pub trait Resolver {
+    /// The localised name of a prototype referenced like
+    /// `__ITEM__iron-plate__`. The category is `entity`, `item`, `tile` or
+    /// `fluid`.
+    fn name(&self, category: &str, name: &str) -> String;
+
+    /// The keys bound to a control. By default the name of the control, as
+    /// we don't know the player's key bindings.
+    fn control(&self, control: &Control) -> String {
+        match control {
+            Control::Key(name) | Control::Modifier(name) | Control::Alt { name, .. } => {
+                name.clone()
+            }
+            Control::LeftClick | Control::AltLeftClick(_) => "Left mouse button".to_owned(),
+            Control::RightClick | Control::AltRightClick(_) => "Right mouse button".to_owned(),
+            Control::KeyShift => "Shift".to_owned(),
+            Control::KeyCtrl => "Control".to_owned(),
+        }
+    }
+}
+
+impl<F> Resolver for F
+where
+    F: Fn(&str, &str) -> String,
+{
+    fn name(&self, category: &str, name: &str) -> String {
+        self(category, name)
+    }
+}
+
+impl Localization {
+    /// Renders the localization as plain text with the given parameters.
+    /// Rich text tags are kept as they are, and the control style and remark
+    /// color are dropped.
+    pub fn render<R>(&self, parameters: &[String], resolver: &R) -> String
+    where
+        R: Resolver + ?Sized,
+    {
+        let mut output = String::new();
+        for element in &self.elements {
+            match element {
+                LocalizationElement::Text(text) => output.push_str(text),
+                LocalizationElement::Parameter(n) => {
+                    match parameter(parameters, *n) {
+                        Some(value) => output.push_str(value),
+                        None => output.push_str(&format!("__{}__", n)),
+                    }
+                }
+                LocalizationElement::Entity(name) => {
+                    output.push_str(&resolver.name("entity", name))
+                }
+                LocalizationElement::Item(name) => output.push_str(&resolver.name("item", name)),
+                LocalizationElement::Tile(name) => output.push_str(&resolver.name("tile", name)),
+                LocalizationElement::Fluid(name) => output.push_str(&resolver.name("fluid", name)),
+                LocalizationElement::Pluralization {
+                    parameter: n,
+                    rules,
+                } => {
+                    let count = parameter(parameters, *n).and_then(|value| value.parse().ok());
+                    if let Some(rule) = count.and_then(|count| select_rule(rules, count)) {
+                        output.push_str(&rule.text.render(parameters, resolver));
+                    }
+                }
+                LocalizationElement::Control(control) => {
+                    output.push_str(&resolver.control(control))
+                }
+                LocalizationElement::ControlStyleBegin
+                | LocalizationElement::ControlStyleEnd
+                | LocalizationElement::RemarkColorBegin
+                | LocalizationElement::RemarkColorEnd => {}
+                LocalizationElement::RichText { tag, value } => {
+                    output.push_str(&format!("[{}={}]", tag, value.render(parameters, resolver)));
+                }
+                LocalizationElement::RichTextEnd(tag) => {
+                    output.push_str(&format!("[/{}]", tag));
+                }
+            }
+        }
+        output
+    }
+}
+
+/// How deep references like `__ITEM__iron-plate__` are resolved, when the
+/// name they resolve to contains references itself. This stops references
+/// that refer to themselves.
+pub const MAX_REFERENCE_DEPTH: usize = 16;
+
+impl Locale {
+    /// Renders the localization for a key. References are looked up as
+    /// `<category>-name.<name>`, e.g. `item-name.iron-plate`. Returns `None`
+    /// if the key doesn't exist.
+    pub fn render(&self, key: &str, parameters: &[String]) -> Option<String> {
+        self.render_nested(key, parameters, 0)
+    }
+
+    fn render_nested(&self, key: &str, parameters: &[String], depth: usize) -> Option<String> {
+        let localization = self.get(key)?;
+        Some(
+            localization.render(parameters, &|category: &str, name: &str| {
+                if depth >= MAX_REFERENCE_DEPTH {
+                    return unresolved_reference(category, name);
+                }
+                let key = format!("{}-name.{}", category, name);
+                self.render_nested(&key, &[], depth + 1)
+                    .unwrap_or_else(|| unknown_key(&key))
+            }),
+        )
+    }
+}
+
+/// The text the game shows for keys that don't exist.
+pub fn unknown_key(key: &str) -> String {
+    format!("Unknown key: \"{}\"", key)
+}
+
+/// A reference as it is written in a locale file, e.g.
+/// `__ITEM__iron-plate__`, for references that are nested too deeply.
+pub fn unresolved_reference(category: &str, name: &str) -> String {
+    format!("__{}__{}__", category.to_uppercase(), name)
+}
+
+/// Parameters are numbered from 1.
+fn parameter(parameters: &[String], n: u32) -> Option<&String> {
+    parameters.get((n as usize).checked_sub(1)?)
+}
+
+fn select_rule(rules: &[PluralizationRule], count: u64) -> Option<&PluralizationRule> {
+    rules
+        .iter()
+        .find(|rule| rule.patterns.iter().any(|pattern| pattern.matches(count)))
+}
+
+impl PluralizationPattern {
+    pub fn matches(&self, count: u64) -> bool {
+        match self {
+            PluralizationPattern::Number(number) => u64::from(*number) == count,
+            PluralizationPattern::EndsWith(suffix) => {
+                let modulus = 10u64.pow(suffix.to_string().len() as u32);
+                count % modulus == u64::from(*suffix)
+            }
+            PluralizationPattern::Rest => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_renders_parameters_references_and_plurals() {
+        let locale = Locale::parse(
+            "[item-name]\niron-plate=Iron plate\n[test]\ncraft=Craft __1__ __plural_for_parameter_1_{1=plate|ends in 2,ends in 3,ends in 4=plates (few)|rest=plates}__ of __ITEM__iron-plate__\n",
+        )
+        .unwrap();
+
+        let render = |count: &str| locale.render("test.craft", &[count.to_owned()]).unwrap();
+        assert_eq!(render("1"), "Craft 1 plate of Iron plate");
+        assert_eq!(render("22"), "Craft 22 plates (few) of Iron plate");
+        assert_eq!(render("5"), "Craft 5 plates of Iron plate");
+        assert_eq!(
+            locale.render("test.craft", &[]).unwrap(),
+            "Craft __1__  of Iron plate"
+        );
+        assert!(locale.render("test.missing", &[]).is_none());
+    }
+
+    #[test]
+    fn it_stops_at_references_to_themselves() {
+        let locale =
+            Locale::parse("[item-name]\na=__ITEM__a__\nb=B __ITEM__c__\nc=C __ITEM__b__\n")
+                .unwrap();
+
+        assert_eq!(locale.render("item-name.a", &[]).unwrap(), "__ITEM__a__");
+        let rendered = locale.render("item-name.b", &[]).unwrap();
+        assert!(rendered.starts_with("B C B C"));
+        assert!(rendered.ends_with("__ITEM__c__"));
+    }
+}
@@ -1,68 +1,93 @@
-use darling::{
-    FromDeriveInput,
-    FromMeta,
-};
-use heck::{
-    ToKebabCase,
-    ToSnekCase,
-};
+use darling::FromDeriveInput;
 use proc_macro2::{
     Literal,
     TokenStream,
 };
-use quote::{
-    format_ident,
-    quote,
-};
+use quote::quote;
 use syn::{
-    parse_str,
     Data,
-    Path,
+    Fields,
 };
 
-#[derive(Debug, FromMeta, Default)]
-pub(crate) struct ContainerAttributes {
-    inherits: Option<String>,
-    r#abstract: bool,
-}
-
 #[derive(FromDeriveInput)]
-#[darling(attributes(lua), forward_attrs(allow, doc, cfg))]
+#[darling(attributes(prototype), forward_attrs(allow, doc, cfg))]
 pub(crate) struct DeriveOptions {
     ident: syn::Ident,
     attrs: Vec<syn::Attribute>,
+
+    /// The `type` of the prototype in `data.raw`. Abstract prototypes don't
+    /// have one.
     #[darling(default)]
-    prototype: ContainerAttributes,
+    type_name: Option<String>,
+
+    /// The category that the prototype and its descendants share, e.g. in
+    /// migrations. Defaults to the category of the parent, or the `type`.
+    #[darling(default)]
+    category: Option<String>,
 }
 
-pub(crate) fn impl_prototype(_data: Data, options: DeriveOptions) -> TokenStream {
+pub(crate) fn impl_prototype(data: Data, options: DeriveOptions) -> TokenStream {
     let struct_ident = options.ident;
-    let name_camelcase = struct_ident.to_string();
-    let name_kebabcase_lit = Literal::string(&name_camelcase.to_kebab_case());
-    let visit_method = format_ident!("visit_{}", name_camelcase.to_snek_case());
 
-    let inherits_type = if let Some(inherits) = &options.prototype.inherits {
-        let inherits_path: Path = parse_str(&inherits).unwrap();
-        quote! { #inherits_path }
+    let type_name = if let Some(type_name) = &options.type_name {
+        let type_name_lit = Literal::string(type_name);
+        quote! { Some(#type_name_lit) }
     }
     else {
-        quote! { () }
+        quote! { None }
     };
 
-    let type_name = if options.prototype.r#abstract {
-        quote! { None }
+    // The parent prototype is always stored in a field named `parent`.
+    let parent_ty = match data {
+        Data::Struct(data) => {
+            match data.fields {
+                Fields::Named(fields) => {
+                    fields
+                        .named
+                        .into_iter()
+                        .find(|field| field.ident.as_ref().unwrap() == "parent")
+                        .map(|field| field.ty)
+                }
+                _ => None,
+            }
+        }
+        _ => panic!("Prototype can only be derived on structs"),
+    };
+
+    let ancestor_of_parent = if parent_ty.is_some() {
+        quote! { crate::Prototype::ancestor::<P>(&self.parent) }
     }
     else {
-        quote! { Some(#name_kebabcase_lit) }
+        quote! { None }
+    };
+
+    let category = match (&options.category, &parent_ty) {
+        (Some(category), _) => {
+            let category_lit = Literal::string(category);
+            quote! { Some(#category_lit) }
+        }
+        (None, Some(parent_ty)) => {
+            quote! {
+                match <#parent_ty as crate::Prototype>::CATEGORY {
+                    Some(category) => Some(category),
+                    None => #type_name,
+                }
+            }
+        }
+        (None, None) => type_name.clone(),
     };
 
     quote! {
-        impl crate::prototypes::Prototype for #struct_ident {
+        impl crate::Prototype for #struct_ident {
             const TYPE: Option<&'static str> = #type_name;
-            type Inherits = #inherits_type;
 
-            fn accept<V: crate::prototypes::Visitor>(&self, visitor: &mut V) -> Result<(), <V as crate::prototypes::Visitor>::Error> {
-                visitor.#visit_method(self)
+            const CATEGORY: Option<&'static str> = #category;
+
+            fn ancestor<P: 'static>(&self) -> Option<&P> {
+                if let Some(this) = (self as &dyn ::std::any::Any).downcast_ref::<P>() {
+                    return Some(this);
+                }
+                #ancestor_of_parent
             }
         }
     }
@@ -13,25 +13,30 @@ pub type Color = LinSrgba<f32>;
 impl FromLuaTable for Color {
     fn from_lua_table(table: Table) -> Result<Self, Error> {
         let r = to_option::<f32>(table.get::<_, Value>(1)?)?;
-        let mut c = if let Some(r) = r {
+        let (mut c, a) = if let Some(r) = r {
             let g = to_option(table.get::<_, Value>(2)?)?.unwrap_or_default();
             let b = to_option(table.get::<_, Value>(3)?)?.unwrap_or_default();
-            let a = to_option(table.get::<_, Value>(4)?)?.unwrap_or_default();
-            Color::new(r, g, b, a)
+            let a = to_option::<f32>(table.get::<_, Value>(4)?)?;
+            (Color::new(r, g, b, 1.), a)
         }
         else {
             let r = to_option(table.get::<_, Value>("r")?)?.unwrap_or_default();
             let g = to_option(table.get::<_, Value>("g")?)?.unwrap_or_default();
             let b = to_option(table.get::<_, Value>("b")?)?.unwrap_or_default();
-            let a = to_option(table.get::<_, Value>("a")?)?.unwrap_or_default();
-            Color::new(r, g, b, a)
+            let a = to_option::<f32>(table.get::<_, Value>("a")?)?;
+            (Color::new(r, g, b, 1.), a)
         };
 
-        if c.color.red > 1. || c.color.green > 1. || c.color.blue > 1. || c.alpha > 1. {
+        // If any component is greater than 1, all of them are in the range 0-255.
+        // A missing alpha component means fully opaque.
+        if c.color.red > 1. || c.color.green > 1. || c.color.blue > 1. || a.unwrap_or(0.) > 1. {
             c.color.red /= 255.;
             c.color.green /= 255.;
             c.color.blue /= 255.;
-            c.alpha /= 255.;
+            c.alpha = a.map_or(1., |a| a / 255.);
+        }
+        else if let Some(a) = a {
+            c.alpha = a;
         }
 
         Ok(c)
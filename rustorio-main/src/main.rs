@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+mod translations;
+
 use std::{
     fs::File,
     io::{
@@ -15,7 +17,12 @@ use std::{
 };
 
 use color_eyre::eyre::Error;
-use rustorio_loader::Loader;
+use rustorio_loader::{
+    cache::default_cache_dir,
+    discovery,
+    dump::read_data_raw_dump,
+    save::Save,
+};
 use rustorio_prototype::{
     item::ItemPrototype,
     technology::TechnologyPrototype,
@@ -65,12 +72,38 @@ impl Write for OutputFile {
 
 #[derive(Debug, StructOpt)]
 pub struct Args {
+    /// The game's `data` directory. Defaults to the one of the newest Factorio
+    /// installation that can be found.
     #[structopt(long, env = "FACTORIO_DATA")]
-    data_dir: PathBuf,
+    data_dir: Option<PathBuf>,
 
     #[structopt(long, env = "FACTORIO_MODS")]
     mod_dir: Option<PathBuf>,
 
+    /// Load the mods and startup settings of a save game. The mods must be in
+    /// the mod directory, in the versions the game was saved with.
+    #[structopt(long)]
+    save: Option<PathBuf>,
+
+    /// Directory in which the results of the data stage are cached. Defaults
+    /// to `~/.cache/rustorio`.
+    #[structopt(long, env = "RUSTORIO_CACHE")]
+    cache_dir: Option<PathBuf>,
+
+    /// Always run the data stage, without using the cache.
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// Load the prototypes from a `data-raw-dump.json` written by `factorio
+    /// --dump-data`, instead of running the data stage.
+    #[structopt(long)]
+    data_raw_dump: Option<PathBuf>,
+
+    /// Continue the data stage if a mod raises an error, skipping the rest of
+    /// that mod. The results are not cached.
+    #[structopt(long)]
+    skip_failed_mods: bool,
+
     #[structopt(subcommand)]
     command: Command,
 }
@@ -79,17 +112,58 @@ pub struct Args {
 enum Command {
     ListTechnologies,
     ListItems,
+
+    /// Runs the data stage and prints how much time and memory each mod and
+    /// file uses.
+    Profile {
+        /// Also write the profile as Chrome trace events to this file.
+        #[structopt(long)]
+        chrome_trace: Option<PathBuf>,
+    },
+
+    /// Reports missing and inconsistent translations of each mod compared to
+    /// English, and prototypes whose names don't resolve.
+    Translations {
+        /// Only report this mod. Can be given multiple times.
+        #[structopt(long = "mod")]
+        mods: Vec<String>,
+
+        /// Only report this language. Can be given multiple times.
+        #[structopt(long = "language")]
+        languages: Vec<String>,
+    },
 }
 
 impl Args {
     fn run(self) -> Result<(), Error> {
-        let loader = if let Some(mod_dir) = &self.mod_dir {
-            Loader::modded(&self.data_dir, mod_dir)?
+        let builder = discovery::builder(self.data_dir.as_deref(), self.mod_dir.as_deref())?;
+        let loader = match &self.save {
+            Some(path) => Save::open(path)?.loader(builder)?,
+            None => builder.finish()?,
+        };
+
+        if let Command::Profile { chrome_trace } = &self.command {
+            let (_, profile): (Prototypes, _) = loader.data_stage_profiled()?;
+            profile.write_table(stdout())?;
+            if let Some(path) = chrome_trace {
+                profile.write_chrome_trace(BufWriter::new(File::create(path)?))?;
+            }
+            return Ok(());
         }
-        else {
-            Loader::vanilla(&self.data_dir)?
+
+        let cache_dir = self.cache_dir.or_else(default_cache_dir);
+        let prototypes: Prototypes = match (&self.data_raw_dump, cache_dir) {
+            (Some(path), _) => read_data_raw_dump(path)?,
+            (None, _) if self.skip_failed_mods => {
+                let (prototypes, errors) = loader.data_stage_tolerant()?;
+                for error in errors {
+                    log::error!("{}", error);
+                }
+                prototypes
+            }
+            (None, Some(cache_dir)) if !self.no_cache => loader.data_stage_cached(cache_dir)?,
+            _ => loader.data_stage()?,
         };
-        let prototypes: Prototypes = loader.data_stage()?;
 
         match self.command {
             Command::ListTechnologies => {
@@ -108,6 +182,10 @@ impl Args {
                     println!("{}", item.base().name);
                 }
             }
+            Command::Translations { mods, languages } => {
+                translations::write_report(stdout(), &loader, &prototypes, &mods, &languages)?;
+            }
+            Command::Profile { .. } => unreachable!(),
         }
 
         Ok(())
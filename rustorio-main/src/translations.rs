@@ -0,0 +1,110 @@
+//! A report of the translations of each mod, compared to English.
+
+use std::io::Write;
+
+use color_eyre::eyre::Error;
+use rustorio_loader::{
+    Loader,
+    FALLBACK_LANGUAGE,
+};
+use rustorio_locale::coverage::Coverage;
+use rustorio_prototype::{
+    localise::Localiser,
+    Prototypes,
+};
+
+/// Writes the report for the given mods and languages, or all of them if
+/// they are empty.
+pub fn write_report(
+    mut writer: impl Write,
+    loader: &Loader,
+    prototypes: &Prototypes,
+    mods: &[String],
+    languages: &[String],
+) -> Result<(), Error> {
+    let is_selected = |selection: &[String], name: &str| {
+        selection.is_empty() || selection.iter().any(|s| s == name)
+    };
+
+    // English is the reference, so only its names are checked.
+    let mut all_languages = vec![];
+    if is_selected(languages, FALLBACK_LANGUAGE) {
+        all_languages.push(FALLBACK_LANGUAGE.to_owned());
+    }
+
+    for mod_name in loader.locale_mods() {
+        if !is_selected(mods, mod_name) {
+            continue;
+        }
+
+        let reference = loader.mod_locale(mod_name, FALLBACK_LANGUAGE)?;
+        writeln!(writer, "{}", mod_name)?;
+
+        for language in loader.mod_languages(mod_name)? {
+            if language == FALLBACK_LANGUAGE || !is_selected(languages, &language) {
+                continue;
+            }
+
+            let translation = loader.mod_locale(mod_name, &language)?;
+            let coverage = Coverage::compare(&reference, &translation);
+            writeln!(
+                writer,
+                "  {}: {}/{} keys translated",
+                language,
+                coverage.translated(),
+                coverage.total
+            )?;
+            for key in &coverage.missing {
+                writeln!(writer, "    missing: {}", key)?;
+            }
+            for key in &coverage.extra {
+                writeln!(writer, "    extra: {}", key)?;
+            }
+            for mismatch in &coverage.parameter_mismatches {
+                writeln!(writer, "    parameters: {}", mismatch)?;
+            }
+            for error in &coverage.plural_errors {
+                writeln!(writer, "    plural: {}", error)?;
+            }
+
+            if !all_languages.contains(&language) {
+                all_languages.push(language);
+            }
+        }
+    }
+
+    // Names are resolved with all mods, as mods often name their prototypes with
+    // keys of other mods, but without falling back to English.
+    let mut all_prototypes = prototypes.iter_any().collect::<Vec<_>>();
+    all_prototypes.sort_by_key(|prototype| (prototype.type_name(), prototype.name()));
+    all_languages.sort();
+
+    for language in all_languages {
+        let locale = loader.read_locale(&language)?;
+        let localiser = Localiser::with_prototypes(&locale, prototypes);
+
+        let unresolved = all_prototypes
+            .iter()
+            .filter_map(|prototype| {
+                let error = localiser.try_name(prototype).err()?;
+                Some((prototype, error))
+            })
+            .collect::<Vec<_>>();
+        if unresolved.is_empty() {
+            continue;
+        }
+
+        writeln!(writer, "Unresolved names [{}]", language)?;
+        for (prototype, error) in unresolved {
+            writeln!(
+                writer,
+                "  {}.{}: {}",
+                prototype.type_name(),
+                prototype.name(),
+                error
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -8,13 +8,15 @@ use serde::{
 
 use super::{
     Inherits,
+    Prototype,
     PrototypeBase,
 };
 use crate::types::IconSpecification;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "achievement")]
 pub struct AchievementPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -0,0 +1,227 @@
+#[cfg(feature = "serde")]
+use std::collections::HashMap;
+
+#[cfg(feature = "lua-api")]
+use rustorio_lua_api::{
+    mlua::Table,
+    Error,
+    FromLuaTable,
+};
+#[cfg(feature = "serde")]
+use serde::{
+    de::{
+        DeserializeSeed,
+        IgnoredAny,
+    },
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+
+use crate::{
+    achievement::AchievementPrototype,
+    entity::{
+        AssemblingMachinePrototype,
+        BeaconPrototype,
+        EntityPrototype,
+        FurnacePrototype,
+        InserterPrototype,
+        LabPrototype,
+        Loader1x1Prototype,
+        Loader1x2Prototype,
+        MiningDrillPrototype,
+        PipePrototype,
+        PipeToGroundPrototype,
+        PumpPrototype,
+        ResourceCategory,
+        ResourceEntityPrototype,
+        RocketSiloPrototype,
+        SplitterPrototype,
+        TransportBeltPrototype,
+        UndergroundBeltPrototype,
+    },
+    fluid::FluidPrototype,
+    group::{
+        ItemGroup,
+        ItemSubGroup,
+    },
+    item::{
+        AmmoItemPrototype,
+        ArmorPrototype,
+        CapsulePrototype,
+        GunPrototype,
+        ItemPrototype,
+        ModuleCategory,
+        ModulePrototype,
+        RailPlannerPrototype,
+        ToolPrototype,
+    },
+    recipe::{
+        RecipeCategory,
+        RecipePrototype,
+    },
+    technology::TechnologyPrototype,
+    types::IconSpecification,
+    Prototype,
+    PrototypeBase,
+};
+
+macro_rules! any_prototype {
+    ($($variant:ident($ty:ty),)*) => {
+        /// A prototype of any of the non-abstract types that we know about.
+        #[derive(Clone, Debug)]
+        #[cfg_attr(feature = "serde", derive(Serialize), serde(untagged))]
+        #[allow(clippy::large_enum_variant)]
+        pub enum AnyPrototype {
+            $($variant($ty),)*
+        }
+
+        impl AnyPrototype {
+            /// The `type` of the prototype in `data.raw`.
+            pub fn type_name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant(_) => <$ty as Prototype>::TYPE.unwrap(),)*
+                }
+            }
+
+            /// Returns the prototype, or its ancestor of type `P`.
+            pub fn upcast<P: 'static>(&self) -> Option<&P> {
+                match self {
+                    $(Self::$variant(prototype) => prototype.ancestor(),)*
+                }
+            }
+
+            /// Parses a prototype from `data.raw`. Returns `None` if the type is
+            /// not known.
+            #[cfg(feature = "lua-api")]
+            pub fn from_lua_table(type_name: &str, table: Table) -> Result<Option<Self>, Error> {
+                $(
+                    if Some(type_name) == <$ty as Prototype>::TYPE {
+                        return Ok(Some(Self::$variant(<$ty>::from_lua_table(table)?)));
+                    }
+                )*
+                Ok(None)
+            }
+
+            /// Deserializes a map from names to prototypes of the given type.
+            /// Returns `None` if the type is not known.
+            #[cfg(feature = "serde")]
+            pub(crate) fn deserialize_map<'de, D: Deserializer<'de>>(
+                type_name: &str,
+                deserializer: D,
+            ) -> Result<Option<HashMap<String, Self>>, D::Error> {
+                $(
+                    if Some(type_name) == <$ty as Prototype>::TYPE {
+                        let prototypes = HashMap::<String, $ty>::deserialize(deserializer)?;
+                        return Ok(Some(
+                            prototypes
+                                .into_iter()
+                                .map(|(name, prototype)| (name, Self::$variant(prototype)))
+                                .collect(),
+                        ));
+                    }
+                )*
+                IgnoredAny::deserialize(deserializer)?;
+                Ok(None)
+            }
+        }
+
+        $(
+            impl From<$ty> for AnyPrototype {
+                fn from(prototype: $ty) -> Self {
+                    Self::$variant(prototype)
+                }
+            }
+        )*
+    };
+}
+
+any_prototype! {
+    Achievement(AchievementPrototype),
+    Technology(TechnologyPrototype),
+    Recipe(RecipePrototype),
+    RecipeCategory(RecipeCategory),
+    Item(ItemPrototype),
+    Tool(ToolPrototype),
+    Armor(ArmorPrototype),
+    Module(ModulePrototype),
+    ModuleCategory(ModuleCategory),
+    Capsule(CapsulePrototype),
+    Ammo(AmmoItemPrototype),
+    Gun(GunPrototype),
+    RailPlanner(RailPlannerPrototype),
+    ItemGroup(ItemGroup),
+    ItemSubGroup(ItemSubGroup),
+    Fluid(FluidPrototype),
+    Lab(LabPrototype),
+    AssemblingMachine(AssemblingMachinePrototype),
+    Furnace(FurnacePrototype),
+    RocketSilo(RocketSiloPrototype),
+    MiningDrill(MiningDrillPrototype),
+    Beacon(BeaconPrototype),
+    TransportBelt(TransportBeltPrototype),
+    UndergroundBelt(UndergroundBeltPrototype),
+    Splitter(SplitterPrototype),
+    Loader(Loader1x2Prototype),
+    Loader1x1(Loader1x1Prototype),
+    Inserter(InserterPrototype),
+    Pipe(PipePrototype),
+    PipeToGround(PipeToGroundPrototype),
+    Pump(PumpPrototype),
+    Resource(ResourceEntityPrototype),
+    ResourceCategory(ResourceCategory),
+}
+
+impl AnyPrototype {
+    pub fn base(&self) -> &PrototypeBase {
+        self.upcast()
+            .expect("all prototypes inherit from PrototypeBase")
+    }
+
+    pub fn name(&self) -> &str {
+        &self.base().name
+    }
+
+    /// The icon of the prototype, if its type has one.
+    pub fn icon_spec(&self) -> Option<&IconSpecification> {
+        let icon_spec = if let Some(entity) = self.upcast::<EntityPrototype>() {
+            &entity.icon_spec
+        }
+        else if let Some(item) = self.upcast::<ItemPrototype>() {
+            &item.icon_spec
+        }
+        else if let Some(fluid) = self.upcast::<FluidPrototype>() {
+            &fluid.icon_spec
+        }
+        else if let Some(recipe) = self.upcast::<RecipePrototype>() {
+            &recipe.icon_spec
+        }
+        else if let Some(technology) = self.upcast::<TechnologyPrototype>() {
+            &technology.icon_spec
+        }
+        else if let Some(achievement) = self.upcast::<AchievementPrototype>() {
+            &achievement.icon_spec
+        }
+        else if let Some(item_group) = self.upcast::<ItemGroup>() {
+            &item_group.icon_spec
+        }
+        else {
+            return None;
+        };
+        Some(icon_spec)
+    }
+}
+
+/// Deserializes the prototypes of a single type, see
+/// [`AnyPrototype::deserialize_map`].
+#[cfg(feature = "serde")]
+pub(crate) struct TypedPrototypes<'a>(pub &'a str);
+
+#[cfg(feature = "serde")]
+impl<'a, 'de> DeserializeSeed<'de> for TypedPrototypes<'a> {
+    type Value = Option<HashMap<String, AnyPrototype>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        AnyPrototype::deserialize_map(self.0, deserializer)
+    }
+}
@@ -0,0 +1,9 @@
+//! Default values of fields, for `#[serde(default = "...")]`.
+
+pub(crate) fn bool_true() -> bool {
+    true
+}
+
+pub(crate) fn f64_one() -> f64 {
+    1.
+}
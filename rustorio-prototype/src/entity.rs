@@ -1,3 +1,4 @@
+use nalgebra::Vector2;
 #[cfg(feature = "lua-api")]
 use rustorio_lua_api::FromLuaTable;
 #[cfg(feature = "serde")]
@@ -6,29 +7,48 @@ use serde::{
     Serialize,
 };
 
+#[cfg(feature = "serde")]
+use crate::defaults::{
+    bool_true,
+    f64_one,
+};
 use crate::{
-    item::ItemPrototype,
+    group::ItemSubGroup,
+    item::{
+        EffectTypeLimitation,
+        ItemPrototype,
+    },
+    recipe::{
+        RecipeCategory,
+        RecipePrototype,
+    },
     types::{
         Animation,
+        AutoplaceSpecification,
         BoundingBox,
         CollisionMask,
+        Color,
         Energy,
         EnergySource,
         EntityPrototypeFlag,
         EntityPrototypeFlags,
+        FluidBox,
         IconSpecification,
+        ItemStackIndex,
         MinableProperties,
         ModuleSpecification,
         TriggerTargetMask,
     },
     Id,
     Inherits,
+    Prototype,
     PrototypeBase,
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(category = "entity")]
 pub struct EntityPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -71,8 +91,10 @@ pub struct EntityPrototype {
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub minable: Option<MinableProperties>,
 
-    // todo
-    //pub subgroup: Option<Id<ItemSubGroup>>,
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub subgroup: Option<Id<ItemSubGroup>>,
+
     #[cfg_attr(feature = "lua-api", lua(default_with = "true"))]
     #[cfg_attr(feature = "serde", serde(default = "bool_true"))]
     pub allow_copy_paste: bool,
@@ -90,10 +112,6 @@ pub struct EntityPrototype {
     // todo
 }
 
-fn bool_true() -> bool {
-    true
-}
-
 fn default_selection_priority() -> u8 {
     50
 }
@@ -106,7 +124,7 @@ impl Inherits for EntityPrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EntityWithHealthPrototype {
@@ -133,7 +151,7 @@ impl Inherits for EntityWithHealthPrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EntityWithOwnerPrototype {
@@ -158,9 +176,10 @@ impl Inherits for EntityWithOwnerPrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "lab")]
 pub struct LabPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -193,3 +212,639 @@ impl Inherits for LabPrototype {
         &self.parent
     }
 }
+
+/// The abstract base of assembling machines, furnaces and rocket silos.
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CraftingMachinePrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    pub energy_usage: Energy,
+
+    pub crafting_speed: f64,
+
+    pub crafting_categories: Vec<Id<RecipeCategory>>,
+
+    pub energy_source: EnergySource,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub fluid_boxes: Vec<FluidBox>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allowed_effects: EffectTypeLimitation,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub module_specification: ModuleSpecification,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub base_productivity: f64,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "true"))]
+    #[cfg_attr(feature = "serde", serde(default = "bool_true"))]
+    pub return_ingredients_on_change: bool,
+    // todo: graphics (`animation`, `working_visualisations`), `working_sound` and the
+    // options for the entity info icon
+}
+
+impl Inherits for CraftingMachinePrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "assembling-machine")]
+pub struct AssemblingMachinePrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: CraftingMachinePrototype,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub fixed_recipe: Option<Id<RecipePrototype>>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub gui_title_key: Option<String>,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "255"))]
+    #[cfg_attr(feature = "serde", serde(default = "default_ingredient_count"))]
+    pub ingredient_count: u8,
+}
+
+#[cfg(feature = "serde")]
+fn default_ingredient_count() -> u8 {
+    255
+}
+
+impl Inherits for AssemblingMachinePrototype {
+    type Parent = CraftingMachinePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "furnace")]
+pub struct FurnacePrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: CraftingMachinePrototype,
+
+    pub result_inventory_size: ItemStackIndex,
+
+    pub source_inventory_size: ItemStackIndex,
+}
+
+impl Inherits for FurnacePrototype {
+    type Parent = CraftingMachinePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "rocket-silo")]
+pub struct RocketSiloPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: AssemblingMachinePrototype,
+
+    pub active_energy_usage: Energy,
+
+    pub idle_energy_usage: Energy,
+
+    pub lamp_energy_usage: Energy,
+
+    pub rocket_entity: String,
+
+    pub rocket_parts_required: u32,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1"))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_rocket_result_inventory_size")
+    )]
+    pub rocket_result_inventory_size: ItemStackIndex,
+    // todo: graphics, sounds and the timings of the launch, e.g. `door_opening_speed`,
+    // `rocket_rising_delay` and `launch_wait_time`
+}
+
+#[cfg(feature = "serde")]
+fn default_rocket_result_inventory_size() -> ItemStackIndex {
+    1
+}
+
+impl Inherits for RocketSiloPrototype {
+    type Parent = AssemblingMachinePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "mining-drill")]
+pub struct MiningDrillPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    pub vector_to_place_result: Vector2<f64>,
+
+    pub resource_searching_radius: f64,
+
+    pub energy_usage: Energy,
+
+    pub mining_speed: f64,
+
+    pub energy_source: EnergySource,
+
+    pub resource_categories: Vec<Id<ResourceCategory>>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub output_fluid_box: Option<FluidBox>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub input_fluid_box: Option<FluidBox>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allowed_effects: EffectTypeLimitation,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub module_specification: ModuleSpecification,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub base_productivity: f64,
+    // todo: graphics (`animations`, `graphics_set`, `wet_mining_graphics_set`), sounds
+    // and circuit connections
+}
+
+impl Inherits for MiningDrillPrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "beacon")]
+pub struct BeaconPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    pub energy_usage: Energy,
+
+    pub energy_source: EnergySource,
+
+    pub supply_area_distance: f64,
+
+    pub distribution_effectivity: f64,
+
+    pub module_specification: ModuleSpecification,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allowed_effects: EffectTypeLimitation,
+    // todo: graphics (`animation`, `base_picture`, `graphics_set`) and
+    // `radius_visualisation_picture`
+}
+
+impl Inherits for BeaconPrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+/// The abstract base of belts, undergrounds, splitters and loaders.
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransportBeltConnectablePrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    /// The speed of the belt in tiles per tick.
+    pub speed: f64,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1."))]
+    #[cfg_attr(feature = "serde", serde(default = "f64_one"))]
+    pub animation_speed_coefficient: f64,
+    // todo: graphics (`belt_animation_set`)
+}
+
+impl Inherits for TransportBeltConnectablePrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "transport-belt")]
+pub struct TransportBeltPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: TransportBeltConnectablePrototype,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub related_underground_belt: Option<Id<UndergroundBeltPrototype>>,
+    // todo: graphics and circuit connections (`circuit_wire_max_distance`,
+    // `connector_frame_sprites`)
+}
+
+impl Inherits for TransportBeltPrototype {
+    type Parent = TransportBeltConnectablePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "underground-belt")]
+pub struct UndergroundBeltPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: TransportBeltConnectablePrototype,
+
+    pub max_distance: u8,
+    // todo: graphics (`structure`, `underground_sprite`)
+}
+
+impl Inherits for UndergroundBeltPrototype {
+    type Parent = TransportBeltConnectablePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "splitter")]
+pub struct SplitterPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: TransportBeltConnectablePrototype,
+    // todo: graphics (`structure`, `structure_patch`)
+}
+
+impl Inherits for SplitterPrototype {
+    type Parent = TransportBeltConnectablePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+/// The abstract base of the 1x2 and 1x1 loaders.
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LoaderPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: TransportBeltConnectablePrototype,
+
+    pub filter_count: u8,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1.5"))]
+    #[cfg_attr(feature = "serde", serde(default = "default_container_distance"))]
+    pub container_distance: f64,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "0.5"))]
+    #[cfg_attr(feature = "serde", serde(default = "default_belt_length"))]
+    pub belt_length: f64,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_rackless_insertion: bool,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "true"))]
+    #[cfg_attr(feature = "serde", serde(default = "bool_true"))]
+    pub allow_container_interaction: bool,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub energy_source: Option<EnergySource>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub energy_per_item: Option<Energy>,
+    // todo: graphics (`structure`, `structure_render_layer`)
+}
+
+#[cfg(feature = "serde")]
+fn default_container_distance() -> f64 {
+    1.5
+}
+
+#[cfg(feature = "serde")]
+fn default_belt_length() -> f64 {
+    0.5
+}
+
+impl Inherits for LoaderPrototype {
+    type Parent = TransportBeltConnectablePrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "loader")]
+pub struct Loader1x2Prototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: LoaderPrototype,
+}
+
+impl Inherits for Loader1x2Prototype {
+    type Parent = LoaderPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "loader-1x1")]
+pub struct Loader1x1Prototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: LoaderPrototype,
+}
+
+impl Inherits for Loader1x1Prototype {
+    type Parent = LoaderPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "inserter")]
+pub struct InserterPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    pub extension_speed: f64,
+
+    /// The rotation speed in revolutions per tick.
+    pub rotation_speed: f64,
+
+    pub insert_position: Vector2<f64>,
+
+    pub pickup_position: Vector2<f64>,
+
+    pub energy_source: EnergySource,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub energy_per_movement: Option<Energy>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub energy_per_rotation: Option<Energy>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stack: bool,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_custom_vectors: bool,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter_count: u8,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "true"))]
+    #[cfg_attr(feature = "serde", serde(default = "bool_true"))]
+    pub chases_belt_items: bool,
+    // todo: graphics (`hand_*` sprites, `platform_picture`), `draw_held_item` and circuit
+    // connections
+}
+
+impl Inherits for InserterPrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "pipe")]
+pub struct PipePrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    pub fluid_box: FluidBox,
+    // todo: graphics (`pictures`) and the window bounding boxes
+}
+
+impl Inherits for PipePrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "pipe-to-ground")]
+pub struct PipeToGroundPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    /// The maximum underground distance is set on the fluid box's pipe
+    /// connections.
+    pub fluid_box: FluidBox,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub draw_fluid_icon_override: bool,
+    // todo: graphics (`pictures`)
+}
+
+impl Inherits for PipeToGroundPrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "pump")]
+pub struct PumpPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityWithOwnerPrototype,
+
+    pub fluid_box: FluidBox,
+
+    pub energy_source: EnergySource,
+
+    pub energy_usage: Energy,
+
+    /// The amount of fluid pumped per tick.
+    pub pumping_speed: f64,
+    // todo: graphics (`animations`, `fluid_animation`, `glass_pictures`) and circuit
+    // connections
+}
+
+impl Inherits for PumpPrototype {
+    type Parent = EntityWithOwnerPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+/// Ores, stone, oil and other resources that can be mined by a mining drill.
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "resource")]
+pub struct ResourceEntityPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: EntityPrototype,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "\"basic-solid\".into()"))]
+    #[cfg_attr(feature = "serde", serde(default = "default_resource_category"))]
+    pub category: Id<ResourceCategory>,
+
+    pub map_color: Color,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub autoplace: Option<AutoplaceSpecification>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub infinite: bool,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub minimum: u32,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1"))]
+    #[cfg_attr(feature = "serde", serde(default = "u32_one"))]
+    pub normal: u32,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1"))]
+    #[cfg_attr(feature = "serde", serde(default = "u32_one"))]
+    pub infinite_depletion_amount: u32,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "3"))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_resource_patch_search_radius")
+    )]
+    pub resource_patch_search_radius: u32,
+    // todo: graphics (`stages`, `stage_counts`, `stages_effect`),
+    // `mining_visualisation_tint` and `tree_removal_probability`
+}
+
+#[cfg(feature = "serde")]
+fn default_resource_category() -> Id<ResourceCategory> {
+    "basic-solid".into()
+}
+
+#[cfg(feature = "serde")]
+fn u32_one() -> u32 {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn default_resource_patch_search_radius() -> u32 {
+    3
+}
+
+impl Inherits for ResourceEntityPrototype {
+    type Parent = EntityPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "resource-category")]
+pub struct ResourceCategory {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: PrototypeBase,
+}
+
+impl Inherits for ResourceCategory {
+    type Parent = PrototypeBase;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
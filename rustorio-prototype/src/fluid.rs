@@ -6,14 +6,89 @@ use serde::{
     Serialize,
 };
 
-use super::Id;
-use crate::types::MaterialAmountType;
+use super::{
+    Id,
+    Inherits,
+    Prototype,
+    PrototypeBase,
+};
+#[cfg(feature = "serde")]
+use crate::defaults::{
+    bool_true,
+    f64_one,
+};
+use crate::{
+    group::ItemSubGroup,
+    types::{
+        Color,
+        Energy,
+        IconSpecification,
+        MaterialAmountType,
+    },
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "fluid")]
 pub struct FluidPrototype {
-    // todo
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: PrototypeBase,
+
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    pub icon_spec: IconSpecification,
+
+    pub default_temperature: f64,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_temperature: Option<f64>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub heat_capacity: Option<Energy>,
+
+    pub base_color: Color,
+
+    pub flow_color: Color,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub gas_temperature: Option<f64>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub fuel_value: Option<Energy>,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1."))]
+    #[cfg_attr(feature = "serde", serde(default = "f64_one"))]
+    pub emissions_multiplier: f64,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "\"fluid\".into()"))]
+    #[cfg_attr(feature = "serde", serde(default = "default_subgroup"))]
+    pub subgroup: Id<ItemSubGroup>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden: bool,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "true"))]
+    #[cfg_attr(feature = "serde", serde(default = "bool_true"))]
+    pub auto_barrel: bool,
+}
+
+#[cfg(feature = "serde")]
+fn default_subgroup() -> Id<ItemSubGroup> {
+    "fluid".into()
+}
+
+impl Inherits for FluidPrototype {
+    type Parent = PrototypeBase;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -44,6 +119,9 @@ pub struct FluidIngredientPrototype {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FluidProductPrototype {
     pub name: Id<FluidPrototype>,
+    /// Only used if `amount_min` and `amount_max` are not set.
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
     pub amount: f64,
     #[cfg_attr(feature = "lua-api", lua(default))]
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
@@ -0,0 +1,67 @@
+#[cfg(feature = "lua-api")]
+use rustorio_lua_api::FromLuaTable;
+#[cfg(feature = "serde")]
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    Id,
+    Inherits,
+    Prototype,
+    PrototypeBase,
+};
+use crate::types::{
+    IconSpecification,
+    Order,
+};
+
+/// A tab in the crafting menu, e.g. "Logistics" or "Production".
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "item-group")]
+pub struct ItemGroup {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: PrototypeBase,
+
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    pub icon_spec: IconSpecification,
+
+    /// Overrides the order of the group in the recipe selection of crafting
+    /// machines.
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub order_in_recipe: Option<Order>,
+}
+
+impl Inherits for ItemGroup {
+    type Parent = PrototypeBase;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+/// A row within an [`ItemGroup`].
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "item-subgroup")]
+pub struct ItemSubGroup {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: PrototypeBase,
+
+    pub group: Id<ItemGroup>,
+}
+
+impl Inherits for ItemSubGroup {
+    type Parent = PrototypeBase;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
@@ -1,5 +1,10 @@
 #[cfg(feature = "lua-api")]
-use rustorio_lua_api::FromLuaTable;
+use rustorio_lua_api::{
+    Error,
+    FromLuaTable,
+    FromLuaValue,
+    Value,
+};
 #[cfg(feature = "serde")]
 use serde::{
     Deserialize,
@@ -7,9 +12,11 @@ use serde::{
 };
 
 use super::{
+    group::ItemSubGroup,
     recipe::RecipePrototype,
     Id,
     Inherits,
+    Prototype,
     PrototypeBase,
 };
 use crate::types::{
@@ -22,13 +29,15 @@ use crate::types::{
     IconSpecification,
     ItemCountType,
     ItemPrototypeFlags,
+    ItemStackIndex,
     PlaceAsTile,
     SpriteVariations,
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "item")]
 pub struct ItemPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -39,6 +48,10 @@ pub struct ItemPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     pub icon_spec: IconSpecification,
 
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub subgroup: Option<Id<ItemSubGroup>>,
+
     #[cfg_attr(feature = "lua-api", lua(default))]
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub place_result: Option<Id<EntityPrototype>>,
@@ -169,9 +182,10 @@ impl From<(Id<ItemPrototype>, u16)> for ItemProductPrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "tool")]
 pub struct ToolPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -201,9 +215,129 @@ impl Inherits for ToolPrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "armor")]
+pub struct ArmorPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: ToolPrototype,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub equipment_grid: Option<String>,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub inventory_size_bonus: Option<ItemStackIndex>,
+    // todo: `resistances`
+}
+
+impl Inherits for ArmorPrototype {
+    type Parent = ToolPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "capsule")]
+pub struct CapsulePrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: ItemPrototype,
+    // todo: `capsule_action` and `radius_color`
+}
+
+impl Inherits for CapsulePrototype {
+    type Parent = ItemPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "ammo")]
+pub struct AmmoItemPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: ItemPrototype,
+
+    #[cfg_attr(feature = "lua-api", lua(default_with = "1."))]
+    #[cfg_attr(feature = "serde", serde(default = "f32_one"))]
+    pub magazine_size: f32,
+
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reload_time: f32,
+    // todo: `ammo_type`
+}
+
+#[cfg(feature = "serde")]
+fn f32_one() -> f32 {
+    1.
+}
+
+impl Inherits for AmmoItemPrototype {
+    type Parent = ItemPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "gun")]
+pub struct GunPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: ItemPrototype,
+    // todo: `attack_parameters`
+}
+
+impl Inherits for GunPrototype {
+    type Parent = ItemPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "rail-planner")]
+pub struct RailPlannerPrototype {
+    #[cfg_attr(feature = "lua-api", lua(flatten))]
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub parent: ItemPrototype,
+
+    pub straight_rail: Id<EntityPrototype>,
+
+    pub curved_rail: Id<EntityPrototype>,
+}
+
+impl Inherits for RailPlannerPrototype {
+    type Parent = ItemPrototype;
+
+    fn parent(&self) -> &Self::Parent {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Prototype)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "module")]
 pub struct ModulePrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -253,9 +387,10 @@ impl Inherits for ModulePrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "module-category")]
 pub struct ModuleCategory {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -291,10 +426,41 @@ pub struct Effect {
     pub pollution: Option<EffectValue>,
 }
 
-#[derive(Clone, Debug)]
-#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct EffectTypeLimitation();
+/// The effects that modules and beacons may apply to an entity. An empty
+/// limitation allows no effects at all.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct EffectTypeLimitation(pub Vec<EffectType>);
+
+impl EffectTypeLimitation {
+    pub fn allows(&self, effect_type: EffectType) -> bool {
+        self.0.contains(&effect_type)
+    }
+}
+
+#[cfg(feature = "lua-api")]
+impl FromLuaValue for EffectTypeLimitation {
+    fn from_lua_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(_) => Ok(Self(vec![EffectType::from_lua_value(value)?])),
+            value => Ok(Self(Vec::from_lua_value(value)?)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "lua-api", derive(FromLuaValue))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum EffectType {
+    Speed,
+    Productivity,
+    Consumption,
+    Pollution,
+}
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
@@ -1,20 +1,28 @@
 pub mod achievement;
+mod any;
+#[cfg(feature = "serde")]
+mod defaults;
 pub mod entity;
 pub mod fluid;
+pub mod group;
 pub mod item;
+pub mod localise;
 pub mod material;
 pub mod recipe;
+pub mod rich_text;
 pub mod technology;
 pub mod types;
 
 use std::{
-    collections::HashMap,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
     hash::Hash,
     marker::PhantomData,
     sync::Arc,
 };
 
-use entity::LabPrototype;
 #[cfg(feature = "lua-api")]
 use rustorio_lua_api::{
     mlua::{
@@ -24,22 +32,16 @@ use rustorio_lua_api::{
     FromLuaTable,
     FromLuaValue,
 };
+pub use rustorio_lua_api_derive::Prototype;
 #[cfg(feature = "serde")]
 use serde::{
     Deserialize,
     Serialize,
 };
 
-use self::{
-    achievement::AchievementPrototype,
-    item::{
-        ItemPrototype,
-        ModulePrototype,
-        ToolPrototype,
-    },
-    recipe::RecipePrototype,
-    technology::TechnologyPrototype,
-};
+pub use crate::any::AnyPrototype;
+#[cfg(feature = "serde")]
+use crate::any::TypedPrototypes;
 use crate::types::{
     LocalisedString,
     Order,
@@ -67,7 +69,7 @@ where
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PrototypeBase {
@@ -95,157 +97,165 @@ impl InheritsBase for PrototypeBase {
     }
 }
 
-#[derive(Clone, Debug)]
-#[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Prototypes {
-    #[cfg_attr(feature = "serde", serde(default))]
-    achievement: PrototypeMap<AchievementPrototype>,
-
-    #[cfg_attr(feature = "serde", serde(default))]
-    technology: PrototypeMap<TechnologyPrototype>,
-
-    #[cfg_attr(feature = "serde", serde(default))]
-    recipe: PrototypeMap<RecipePrototype>,
-
-    #[cfg_attr(feature = "serde", serde(default))]
-    item: PrototypeMap<ItemPrototype>,
-
-    #[cfg_attr(feature = "serde", serde(default))]
-    tool: PrototypeMap<ToolPrototype>,
-
-    #[cfg_attr(feature = "serde", serde(default))]
-    module: PrototypeMap<ModulePrototype>,
-
-    #[cfg_attr(feature = "serde", serde(default))]
-    lab: PrototypeMap<LabPrototype>,
-}
-
-pub trait HasPrototypes<P: 'static> {
-    fn get(&self, id: &Id<P>) -> Option<&P>;
-
-    fn try_get(&self, id: &Id<P>) -> Result<&P, PrototypeNotFound> {
-        self.get(id)
-            .ok_or_else(|| PrototypeNotFound { id: id.to_string() })
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &P>;
-}
-
-macro_rules! return_some {
-    ($opt:expr) => {
-        if let Some(value) = $opt {
-            return Some(value);
-        }
-    };
-}
-
-impl HasPrototypes<AchievementPrototype> for Prototypes {
-    fn get(&self, id: &Id<AchievementPrototype>) -> Option<&AchievementPrototype> {
-        self.achievement.get(id)
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &AchievementPrototype> {
-        self.achievement.iter()
-    }
-}
-
-impl HasPrototypes<TechnologyPrototype> for Prototypes {
-    fn get(&self, id: &Id<TechnologyPrototype>) -> Option<&TechnologyPrototype> {
-        self.technology.get(id)
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &TechnologyPrototype> {
-        self.technology.iter()
-    }
+/// Implemented by all prototypes, usually with `#[derive(Prototype)]`.
+///
+/// The derive uses the field named `parent` to walk up the [`Inherits`] chain,
+/// `#[prototype(type_name = "...")]` for the type in `data.raw` and
+/// `#[prototype(category = "...")]` for the category of abstract prototypes.
+pub trait Prototype: Sized + 'static {
+    /// The `type` of the prototype in `data.raw`, or `None` for abstract
+    /// prototypes.
+    const TYPE: Option<&'static str>;
+
+    /// The category that the prototype shares with the other descendants of
+    /// its topmost ancestor with a type, e.g. `item` for tools and modules,
+    /// and `entity` for all entities. Migrations rename prototypes by it.
+    const CATEGORY: Option<&'static str>;
+
+    /// Returns `self` if it is a `P`, or the ancestor of type `P`.
+    fn ancestor<P: 'static>(&self) -> Option<&P>;
 }
 
-impl HasPrototypes<RecipePrototype> for Prototypes {
-    fn get(&self, id: &Id<RecipePrototype>) -> Option<&RecipePrototype> {
-        self.recipe.get(id)
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &RecipePrototype> {
-        self.recipe.iter()
-    }
+/// All prototypes by type and name.
+///
+/// Prototypes can be looked up either with their concrete type or with any of
+/// their ancestors, e.g. `HasPrototypes<ItemPrototype>` also finds tools and
+/// modules.
+#[derive(Clone, Debug, Default)]
+pub struct Prototypes {
+    by_type: BTreeMap<String, HashMap<String, AnyPrototype>>,
 }
 
-impl HasPrototypes<ItemPrototype> for Prototypes {
-    fn get(&self, id: &Id<ItemPrototype>) -> Option<&ItemPrototype> {
-        return_some!(self.item.get(id));
-        return_some!(self.tool.get(&id.downcast()).map(|x| x.parent()));
-        return_some!(self.module.get(&id.downcast()).map(|x| x.parent()));
-        None
+impl Prototypes {
+    pub fn get_any(&self, type_name: &str, name: &str) -> Option<&AnyPrototype> {
+        self.by_type.get(type_name)?.get(name)
     }
 
-    fn iter(&self) -> impl Iterator<Item = &ItemPrototype> {
-        self.item
-            .iter()
-            .chain(self.tool.iter().map(|x| x.parent()))
-            .chain(self.module.iter().map(|x| x.parent()))
+    pub fn iter_any(&self) -> impl Iterator<Item = &AnyPrototype> {
+        self.by_type
+            .values()
+            .flat_map(|prototypes| prototypes.values())
     }
-}
 
-impl HasPrototypes<ToolPrototype> for Prototypes {
-    fn get(&self, id: &Id<ToolPrototype>) -> Option<&ToolPrototype> {
-        self.tool.get(id)
+    /// The types that have at least one prototype.
+    pub fn types(&self) -> impl Iterator<Item = &str> {
+        self.by_type.keys().map(|type_name| type_name.as_str())
     }
 
-    fn iter(&self) -> impl Iterator<Item = &ToolPrototype> {
-        self.tool.iter()
+    /// Inserts a prototype, returning the prototype of the same type and name
+    /// that it replaces.
+    pub fn insert(&mut self, prototype: impl Into<AnyPrototype>) -> Option<AnyPrototype> {
+        let prototype = prototype.into();
+        self.by_type
+            .entry(prototype.type_name().to_owned())
+            .or_default()
+            .insert(prototype.name().to_owned(), prototype)
     }
 }
 
-impl HasPrototypes<ModulePrototype> for Prototypes {
-    fn get(&self, id: &Id<ModulePrototype>) -> Option<&ModulePrototype> {
-        self.module.get(id)
-    }
+#[cfg(feature = "lua-api")]
+impl FromLuaTable for Prototypes {
+    fn from_lua_table(table: Table) -> Result<Self, rustorio_lua_api::Error> {
+        let mut prototypes = Self::default();
+
+        for r in table.pairs::<String, Table>() {
+            let (type_name, table) = r?;
+
+            for r in table.pairs::<String, Table>() {
+                let (name, table) = r?;
+
+                match AnyPrototype::from_lua_table(&type_name, table)? {
+                    Some(prototype) => {
+                        prototypes
+                            .by_type
+                            .entry(type_name.clone())
+                            .or_default()
+                            .insert(name, prototype);
+                    }
+                    None => {
+                        log::debug!("Skipping unknown prototype type: {}", type_name);
+                        break;
+                    }
+                }
+            }
+        }
 
-    fn iter(&self) -> impl Iterator<Item = &ModulePrototype> {
-        self.module.iter()
+        Ok(prototypes)
     }
 }
 
-impl HasPrototypes<LabPrototype> for Prototypes {
-    fn get(&self, id: &Id<LabPrototype>) -> Option<&LabPrototype> {
-        self.lab.get(id)
-    }
-
-    fn iter(&self) -> impl Iterator<Item = &LabPrototype> {
-        self.lab.iter()
+#[cfg(feature = "serde")]
+impl Serialize for Prototypes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.by_type.serialize(serializer)
     }
 }
 
-#[derive(Clone, Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
-pub struct PrototypeMap<P>(HashMap<String, P>);
-
-impl<P> Default for PrototypeMap<P> {
-    fn default() -> Self {
-        Self(Default::default())
-    }
-}
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Prototypes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Prototypes;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map of prototype types to prototypes")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut prototypes = Prototypes::default();
+                while let Some(type_name) = map.next_key::<String>()? {
+                    if let Some(by_name) = map.next_value_seed(TypedPrototypes(&type_name))? {
+                        prototypes.by_type.insert(type_name, by_name);
+                    }
+                }
+                Ok(prototypes)
+            }
+        }
 
-#[cfg(feature = "lua-api")]
-impl<P: FromLuaTable> FromLuaTable for PrototypeMap<P> {
-    fn from_lua_table(table: Table) -> Result<Self, rustorio_lua_api::Error> {
-        let d: HashMap<String, P> = FromLuaTable::from_lua_table(table)?;
-        Ok(Self(d))
+        deserializer.deserialize_map(Visitor)
     }
 }
 
-impl<P> PrototypeMap<P> {
-    pub fn get(&self, id: &Id<P>) -> Option<&P> {
-        self.0.get(id.as_str())
-    }
+pub trait HasPrototypes<P: 'static> {
+    fn get(&self, id: &Id<P>) -> Option<&P>;
 
-    pub fn try_get(&self, id: &Id<P>) -> Result<&P, PrototypeNotFound> {
+    fn try_get(&self, id: &Id<P>) -> Result<&P, PrototypeNotFound> {
         self.get(id)
             .ok_or_else(|| PrototypeNotFound { id: id.to_string() })
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &P> {
-        self.0.values()
+    fn iter(&self) -> impl Iterator<Item = &P>;
+}
+
+impl<P: Prototype> HasPrototypes<P> for Prototypes {
+    fn get(&self, id: &Id<P>) -> Option<&P> {
+        // Subtypes share the names of their ancestors, e.g. a tool can't have the
+        // same name as an item.
+        self.by_type
+            .values()
+            .find_map(|prototypes| prototypes.get(id.as_str())?.upcast())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &P> {
+        self.by_type
+            .values()
+            .filter(|prototypes| {
+                // All prototypes in a map have the same type.
+                prototypes
+                    .values()
+                    .next()
+                    .is_some_and(|prototype| prototype.upcast::<P>().is_some())
+            })
+            .flat_map(|prototypes| {
+                prototypes
+                    .values()
+                    .filter_map(|prototype| prototype.upcast())
+            })
     }
 }
 
@@ -0,0 +1,352 @@
+//! Rendering [`LocalisedString`]s and the names of prototypes with a
+//! [`Locale`].
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use rustorio_locale::{
+    unknown_key,
+    unresolved_reference,
+    Locale,
+    Resolver,
+    MAX_REFERENCE_DEPTH,
+};
+
+use crate::{
+    entity::EntityPrototype,
+    item::ItemPrototype,
+    recipe::RecipePrototype,
+    technology::TechnologyPrototype,
+    types::LocalisedString,
+    AnyPrototype,
+    Prototypes,
+};
+
+lazy_static! {
+    static ref TECHNOLOGY_LEVEL_REGEX: Regex = Regex::new(r"^(.+)-(\d+)$").unwrap();
+}
+
+/// The locale category of a prototype, e.g. `entity` for all entities and
+/// `item` for all items. Keys are `<category>-name.<name>`.
+pub fn locale_category(prototype: &AnyPrototype) -> &'static str {
+    if prototype.upcast::<EntityPrototype>().is_some() {
+        "entity"
+    }
+    else if prototype.upcast::<ItemPrototype>().is_some() {
+        "item"
+    }
+    else {
+        prototype.type_name()
+    }
+}
+
+/// Finds a prototype by its locale category, e.g. `entity`, and name.
+pub(crate) fn find_by_category<'a>(
+    prototypes: &'a Prototypes,
+    category: &str,
+    name: &str,
+) -> Option<&'a AnyPrototype> {
+    prototypes
+        .types()
+        .filter_map(|type_name| prototypes.get_any(type_name, name))
+        .find(|prototype| locale_category(prototype) == category)
+}
+
+/// Renders localised strings. With prototypes, references like
+/// `__ENTITY__assembling-machine-1__` and the names of prototypes without a
+/// `localised_name` are resolved like the game does.
+#[derive(Clone, Copy, Debug)]
+pub struct Localiser<'a> {
+    locale: &'a Locale,
+    prototypes: Option<&'a Prototypes>,
+
+    /// How many references deep the localiser is, see [`Resolver::name`].
+    depth: usize,
+}
+
+impl<'a> Localiser<'a> {
+    pub fn new(locale: &'a Locale) -> Self {
+        Self {
+            locale,
+            prototypes: None,
+            depth: 0,
+        }
+    }
+
+    pub fn with_prototypes(locale: &'a Locale, prototypes: &'a Prototypes) -> Self {
+        Self {
+            locale,
+            prototypes: Some(prototypes),
+            depth: 0,
+        }
+    }
+
+    pub fn resolve(&self, string: &LocalisedString) -> String {
+        self.try_resolve(string).unwrap_or_else(|rendered| rendered)
+    }
+
+    /// Like [`Localiser::resolve`], but returns the rendered string as error if
+    /// any key is unknown.
+    pub fn try_resolve(&self, string: &LocalisedString) -> Result<String, String> {
+        let (key, parameters) = match string {
+            LocalisedString::String(s) => return Ok(s.clone()),
+            LocalisedString::Bool(b) => return Ok(b.to_string()),
+            LocalisedString::Number(n) => return Ok(n.to_string()),
+            LocalisedString::Table { key, parameters } => (key, parameters),
+        };
+
+        match key.as_str() {
+            // Concatenates the parameters.
+            "" => {
+                let mut output = String::new();
+                let mut is_complete = true;
+                for parameter in parameters {
+                    let rendered = self.try_resolve(parameter).unwrap_or_else(|rendered| {
+                        is_complete = false;
+                        rendered
+                    });
+                    output.push_str(&rendered);
+                }
+                if is_complete {
+                    Ok(output)
+                }
+                else {
+                    Err(output)
+                }
+            }
+            // Uses the first parameter that has no unknown keys.
+            "?" => {
+                let mut last = Err(String::new());
+                for parameter in parameters {
+                    last = self.try_resolve(parameter);
+                    if last.is_ok() {
+                        break;
+                    }
+                }
+                last
+            }
+            key => {
+                let localization = self.locale.get(key).ok_or_else(|| unknown_key(key))?;
+                let mut is_complete = true;
+                let parameters = parameters
+                    .iter()
+                    .map(|parameter| {
+                        self.try_resolve(parameter).unwrap_or_else(|rendered| {
+                            is_complete = false;
+                            rendered
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let output = localization.render(&parameters, self);
+                if is_complete {
+                    Ok(output)
+                }
+                else {
+                    Err(output)
+                }
+            }
+        }
+    }
+
+    /// The localised name of a prototype. If the prototype has no
+    /// `localised_name`, the key `<category>-name.<name>` is used. Items fall
+    /// back to the entity they place, recipes to their main product and
+    /// technologies with a level, e.g. `mining-productivity-2`, to the name
+    /// without it.
+    pub fn name(&self, prototype: &AnyPrototype) -> String {
+        self.try_name(prototype).unwrap_or_else(|rendered| rendered)
+    }
+
+    pub fn try_name(&self, prototype: &AnyPrototype) -> Result<String, String> {
+        let base = prototype.base();
+        if let Some(localised_name) = &base.localised_name {
+            return self.try_resolve(localised_name);
+        }
+
+        let key = format!("{}-name.{}", locale_category(prototype), base.name);
+        if let Some(rendered) = self.render_key(&key) {
+            return Ok(rendered);
+        }
+
+        self.fallback_name(prototype)
+            .ok_or_else(|| unknown_key(&key))
+    }
+
+    /// The localised description of a prototype, if it has one.
+    pub fn description(&self, prototype: &AnyPrototype) -> Option<String> {
+        let base = prototype.base();
+        if let Some(localised_description) = &base.localised_description {
+            return self.try_resolve(localised_description).ok();
+        }
+        let key = format!("{}-description.{}", locale_category(prototype), base.name);
+        self.render_key(&key)
+    }
+
+    fn fallback_name(&self, prototype: &AnyPrototype) -> Option<String> {
+        if let Some(item) = prototype.upcast::<ItemPrototype>() {
+            let place_result = item.place_result.as_ref()?;
+            return self
+                .find("entity", place_result.as_str())
+                .and_then(|entity| self.try_name(entity).ok());
+        }
+
+        if let Some(recipe) = prototype.upcast::<RecipePrototype>() {
+            let (category, name) = main_product(recipe)?;
+            return self
+                .find(category, &name)
+                .and_then(|product| self.try_name(product).ok());
+        }
+
+        if prototype.upcast::<TechnologyPrototype>().is_some() {
+            let captures = TECHNOLOGY_LEVEL_REGEX.captures(prototype.name())?;
+            let key = format!("technology-name.{}", &captures[1]);
+            return self.render_key(&key);
+        }
+
+        None
+    }
+
+    /// Renders a key without parameters, resolving its references with the
+    /// localiser.
+    fn render_key(&self, key: &str) -> Option<String> {
+        Some(self.locale.get(key)?.render(&[], self))
+    }
+
+    /// Finds a prototype by its locale category and name.
+    fn find(&self, category: &str, name: &str) -> Option<&'a AnyPrototype> {
+        find_by_category(self.prototypes?, category, name)
+    }
+}
+
+impl Resolver for Localiser<'_> {
+    /// Resolves references like `__ENTITY__assembling-machine-1__` to the
+    /// name of the prototype, if it exists. References nested deeper than
+    /// [`MAX_REFERENCE_DEPTH`] are left as they are.
+    fn name(&self, category: &str, name: &str) -> String {
+        if self.depth >= MAX_REFERENCE_DEPTH {
+            return unresolved_reference(category, name);
+        }
+        let nested = Self {
+            depth: self.depth + 1,
+            ..*self
+        };
+
+        match nested.find(category, name) {
+            Some(prototype) => nested.name(prototype),
+            None => {
+                let key = format!("{}-name.{}", category, name);
+                nested.render_key(&key).unwrap_or_else(|| unknown_key(&key))
+            }
+        }
+    }
+}
+
+/// The category and name of the product a recipe is named after: the
+/// `main_product`, or the only product.
+fn main_product(recipe: &RecipePrototype) -> Option<(&'static str, String)> {
+    let data = recipe.data.normal();
+    let product_category = |name: &str| {
+        data.results
+            .iter()
+            .find_map(|result| {
+                if result.as_item()?.name.as_str() == name {
+                    Some("item")
+                }
+                else {
+                    None
+                }
+            })
+            .or_else(|| {
+                data.results
+                    .iter()
+                    .any(|result| {
+                        result
+                            .as_fluid()
+                            .is_some_and(|fluid| fluid.name.as_str() == name)
+                    })
+                    .then_some("fluid")
+            })
+    };
+
+    match &data.main_product {
+        // An empty main product means the recipe has no main product.
+        Some(name) if name.is_empty() => None,
+        Some(name) => Some((product_category(name).unwrap_or("item"), name.clone())),
+        None => {
+            if let Some(result) = &data.result {
+                return Some(("item", result.as_str().to_owned()));
+            }
+            match data.results.as_slice() {
+                [result] => {
+                    if let Some(item) = result.as_item() {
+                        Some(("item", item.name.as_str().to_owned()))
+                    }
+                    else {
+                        result
+                            .as_fluid()
+                            .map(|fluid| ("fluid", fluid.name.as_str().to_owned()))
+                    }
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(key: &str, parameters: Vec<LocalisedString>) -> LocalisedString {
+        LocalisedString::Table {
+            key: key.to_owned(),
+            parameters,
+        }
+    }
+
+    #[test]
+    fn it_resolves_localised_strings() {
+        let locale =
+            Locale::parse("[item-name]\niron-plate=Iron plate\n[gui]\namount=__1__ × __2__\n")
+                .unwrap();
+        let localiser = Localiser::new(&locale);
+
+        let string = table(
+            "",
+            vec![
+                table(
+                    "gui.amount",
+                    vec![
+                        LocalisedString::Number(2),
+                        table("item-name.iron-plate", vec![]),
+                    ],
+                ),
+                LocalisedString::String("!".to_owned()),
+            ],
+        );
+        assert_eq!(localiser.resolve(&string), "2 × Iron plate!");
+
+        let string = table(
+            "?",
+            vec![
+                table("item-name.copper-plate", vec![]),
+                table("item-name.iron-plate", vec![]),
+            ],
+        );
+        assert_eq!(localiser.resolve(&string), "Iron plate");
+        assert_eq!(
+            localiser.resolve(&table("item-name.copper-plate", vec![])),
+            "Unknown key: \"item-name.copper-plate\""
+        );
+    }
+
+    #[test]
+    fn it_stops_at_references_to_themselves() {
+        let locale = Locale::parse("[item-name]\na=__ITEM__a__\n").unwrap();
+        let localiser = Localiser::new(&locale);
+
+        assert_eq!(
+            localiser.resolve(&table("item-name.a", vec![])),
+            "__ITEM__a__"
+        );
+    }
+}
@@ -14,22 +14,24 @@ use super::{
     },
     Id,
     Inherits,
+    Prototype,
     PrototypeBase,
 };
 use crate::{
     fluid::FluidIngredientPrototype,
+    group::ItemSubGroup,
     item::ItemIngredientPrototype,
     types::{
         DifficultyDependentData,
         IconSpecification,
         ItemOrFluid,
-        Todo,
     },
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "recipe")]
 pub struct RecipePrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -41,7 +43,7 @@ pub struct RecipePrototype {
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
-    pub subgroup: Option<Id<Todo>>,
+    pub subgroup: Option<Id<ItemSubGroup>>,
 
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     pub icon_spec: IconSpecification,
@@ -59,15 +61,24 @@ impl Inherits for RecipePrototype {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "recipe-category")]
 pub struct RecipeCategory {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
     pub parent: PrototypeBase,
 }
 
+impl Inherits for RecipeCategory {
+    type Parent = PrototypeBase;
+
+    fn parent(&self) -> &PrototypeBase {
+        &self.parent
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -0,0 +1,632 @@
+//! Rich text, as used in blueprint labels, train stop names, descriptions and
+//! chat messages, e.g. `[item=iron-plate] [color=red]Unload[/color]`.
+//!
+//! See the [wiki](https://wiki.factorio.com/Rich_text) for the tags. Like the
+//! game, the parser never fails: tags it doesn't understand are kept as text,
+//! and tags that are never closed end with the text.
+
+use std::fmt::{
+    self,
+    Display,
+    Formatter,
+    Write,
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use rustorio_locale::Resolver;
+
+use crate::{
+    localise::find_by_category,
+    Prototypes,
+};
+
+lazy_static! {
+    static ref TAG_REGEX: Regex = Regex::new(r"\[(/?)([a-z-]+)(?:=([^\[\]]*))?\]").unwrap();
+}
+
+/// Tags that reference a prototype, e.g. `[item=iron-plate]`. They are also
+/// the classes of `[img=class/name]` that reference a prototype.
+const PROTOTYPE_TAGS: &[&str] = &[
+    "item",
+    "entity",
+    "technology",
+    "recipe",
+    "item-group",
+    "fluid",
+    "tile",
+    "virtual-signal",
+    "achievement",
+    "equipment",
+];
+
+/// The prototype categories that [`Prototypes`] has and that references can be
+/// validated against.
+const VALIDATED_CATEGORIES: &[&str] = &[
+    "item",
+    "entity",
+    "technology",
+    "recipe",
+    "item-group",
+    "fluid",
+    "achievement",
+];
+
+/// Tags that we don't interpret, but know not to be text.
+const OTHER_TAGS: &[&str] = &["special-item", "armor", "train", "train-stop", "tooltip"];
+
+/// The colors that can be used by name, e.g. `[color=red]`.
+const NAMED_COLORS: &[(&str, [f32; 3])] = &[
+    ("default", [1.0, 0.630, 0.259]),
+    ("red", [1.0, 0.166, 0.141]),
+    ("green", [0.173, 0.824, 0.250]),
+    ("blue", [0.343, 0.683, 1.0]),
+    ("orange", [0.869, 0.5, 0.130]),
+    ("yellow", [0.835, 0.666, 0.077]),
+    ("pink", [0.929, 0.386, 0.514]),
+    ("purple", [0.485, 0.111, 0.659]),
+    ("white", [0.9, 0.9, 0.9]),
+    ("black", [0.1, 0.1, 0.1]),
+    ("gray", [0.4, 0.4, 0.4]),
+    ("brown", [0.3, 0.117, 0.0]),
+    ("cyan", [0.275, 0.755, 0.712]),
+    ("acid", [0.559, 0.761, 0.157]),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Span {
+    Text(String),
+
+    /// `[color=red]...[/color]`. The color is a name, `r,g,b` or `#rrggbb`.
+    Color {
+        color: String,
+        spans: Vec<Span>,
+    },
+
+    /// `[font=default-bold]...[/font]`
+    Font {
+        font: String,
+        spans: Vec<Span>,
+    },
+
+    /// A prototype, e.g. `[item=iron-plate]`, or only its icon, e.g.
+    /// `[img=item/iron-plate]`.
+    Prototype {
+        category: String,
+        name: String,
+        is_image: bool,
+    },
+
+    /// An image that isn't a prototype, e.g. `[img=utility/warning_icon]`.
+    Image(String),
+
+    /// `[gps=x,y]` or `[gps=x,y,surface]`
+    Gps {
+        x: f64,
+        y: f64,
+        surface: Option<String>,
+    },
+
+    /// Other tags, e.g. `[train=42]`.
+    Tag {
+        tag: String,
+        value: String,
+    },
+}
+
+/// A reference to a prototype that doesn't exist.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("unknown {category}: {name}")]
+pub struct InvalidReference {
+    pub category: String,
+    pub name: String,
+}
+
+enum Container {
+    Color(String),
+    Font(String),
+}
+
+impl Container {
+    fn tag(&self) -> &'static str {
+        match self {
+            Container::Color(_) => "color",
+            Container::Font(_) => "font",
+        }
+    }
+
+    fn into_span(self, spans: Vec<Span>) -> Span {
+        match self {
+            Container::Color(color) => Span::Color { color, spans },
+            Container::Font(font) => Span::Font { font, spans },
+        }
+    }
+}
+
+impl RichText {
+    pub fn parse(input: &str) -> Self {
+        let mut stack: Vec<(Container, Vec<Span>)> = vec![];
+        let mut spans = vec![];
+        let mut position = 0;
+
+        for captures in TAG_REGEX.captures_iter(input) {
+            let matched = captures.get(0).unwrap();
+            let is_end = !captures[1].is_empty();
+            let tag = &captures[2];
+            let value = captures.get(3).map(|value| value.as_str());
+
+            push_text(
+                current(&mut stack, &mut spans),
+                &input[position..matched.start()],
+            );
+            position = matched.end();
+
+            if is_end {
+                if value.is_none() && stack.iter().any(|(container, _)| container.tag() == tag) {
+                    // Closes the tag and all tags that were opened in it.
+                    while let Some((container, children)) = stack.pop() {
+                        let is_closed = container.tag() == tag;
+                        let span = container.into_span(children);
+                        current(&mut stack, &mut spans).push(span);
+                        if is_closed {
+                            break;
+                        }
+                    }
+                }
+                else {
+                    push_text(current(&mut stack, &mut spans), matched.as_str());
+                }
+                continue;
+            }
+
+            match parse_tag(tag, value) {
+                Some(Tag::Open(container)) => stack.push((container, vec![])),
+                Some(Tag::Span(span)) => current(&mut stack, &mut spans).push(span),
+                None => push_text(current(&mut stack, &mut spans), matched.as_str()),
+            }
+        }
+
+        push_text(current(&mut stack, &mut spans), &input[position..]);
+
+        while let Some((container, children)) = stack.pop() {
+            let span = container.into_span(children);
+            current(&mut stack, &mut spans).push(span);
+        }
+
+        Self { spans }
+    }
+
+    /// Checks that the referenced prototypes exist. Tiles, virtual signals
+    /// and equipment are not checked, as they are not in [`Prototypes`].
+    pub fn validate(&self, prototypes: &Prototypes) -> Vec<InvalidReference> {
+        let mut errors = vec![];
+        visit(&self.spans, &mut |span| {
+            if let Span::Prototype { category, name, .. } = span {
+                if VALIDATED_CATEGORIES.contains(&category.as_str())
+                    && find_by_category(prototypes, category, name).is_none()
+                {
+                    errors.push(InvalidReference {
+                        category: category.clone(),
+                        name: name.clone(),
+                    });
+                }
+            }
+        });
+        errors
+    }
+
+    /// Renders as plain text. Prototypes are replaced with their names, and
+    /// images are dropped.
+    pub fn to_plain<R: Resolver + ?Sized>(&self, resolver: &R) -> String {
+        let mut output = String::new();
+        render_plain(&self.spans, resolver, &mut output);
+        output
+    }
+
+    /// Renders with ANSI escape codes for colors and bold fonts.
+    pub fn to_ansi<R: Resolver + ?Sized>(&self, resolver: &R) -> String {
+        let mut output = String::new();
+        render_ansi(&self.spans, resolver, &mut vec![], &mut output);
+        output
+    }
+
+    /// Renders as HTML. `icon_url` returns the URL of the icon of a prototype
+    /// by category and name, e.g. with [`icon_key`] and the icons of
+    /// the export. Prototypes without an icon are rendered with their names.
+    pub fn to_html<R, I>(&self, resolver: &R, icon_url: &I) -> String
+    where
+        R: Resolver + ?Sized,
+        I: Fn(&str, &str) -> Option<String>,
+    {
+        let mut output = String::new();
+        render_html(&self.spans, resolver, icon_url, &mut output);
+        output
+    }
+}
+
+impl Display for RichText {
+    /// Formats as rich text markup.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.spans.iter().try_for_each(|span| write!(f, "{}", span))
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Span::Text(text) => write!(f, "{}", text),
+            Span::Color { color, spans } => {
+                write!(f, "[color={}]", color)?;
+                spans.iter().try_for_each(|span| write!(f, "{}", span))?;
+                write!(f, "[/color]")
+            }
+            Span::Font { font, spans } => {
+                write!(f, "[font={}]", font)?;
+                spans.iter().try_for_each(|span| write!(f, "{}", span))?;
+                write!(f, "[/font]")
+            }
+            Span::Prototype {
+                category,
+                name,
+                is_image: false,
+            } => write!(f, "[{}={}]", category, name),
+            Span::Prototype {
+                category,
+                name,
+                is_image: true,
+            } => write!(f, "[img={}/{}]", category, name),
+            Span::Image(path) => write!(f, "[img={}]", path),
+            Span::Gps { x, y, surface } => {
+                write!(f, "[gps={},{}", x, y)?;
+                if let Some(surface) = surface {
+                    write!(f, ",{}", surface)?;
+                }
+                write!(f, "]")
+            }
+            Span::Tag { tag, value } => write!(f, "[{}={}]", tag, value),
+        }
+    }
+}
+
+/// Parses a color as the game does: a name, `r,g,b` with values from 0 to 1,
+/// or from 0 to 255 if any value is greater than 1, or `#rrggbb`.
+pub fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let color = color.trim();
+
+    if let Some((_, rgb)) = NAMED_COLORS.iter().find(|(name, _)| *name == color) {
+        return Some(rgb.map(|value| (value * 255.0).round() as u8));
+    }
+
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let [_, r, g, b] = value.to_be_bytes();
+        return Some([r, g, b]);
+    }
+
+    let values = color
+        .split(',')
+        .map(|value| value.trim().parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let [r, g, b] = values[..]
+    else {
+        return None;
+    };
+    let scale = if [r, g, b].iter().any(|value| *value > 1.0) {
+        1.0
+    }
+    else {
+        255.0
+    };
+    Some([r, g, b].map(|value| (value * scale).clamp(0.0, 255.0).round() as u8))
+}
+
+/// The key of the icon of a prototype, by category and name, see
+/// [`IconSpecification::key`](crate::types::IconSpecification::key). The
+/// export maps these to its icon files.
+pub fn icon_key(prototypes: &Prototypes, category: &str, name: &str) -> Option<String> {
+    find_by_category(prototypes, category, name)?
+        .icon_spec()?
+        .key()
+}
+
+enum Tag {
+    Open(Container),
+    Span(Span),
+}
+
+fn parse_tag(tag: &str, value: Option<&str>) -> Option<Tag> {
+    let value = value?;
+
+    let span = match tag {
+        "color" => return Some(Tag::Open(Container::Color(value.to_owned()))),
+        "font" => return Some(Tag::Open(Container::Font(value.to_owned()))),
+        "img" => {
+            match value.split_once('/') {
+                Some((category, name)) if PROTOTYPE_TAGS.contains(&category) => {
+                    Span::Prototype {
+                        category: category.to_owned(),
+                        name: name.to_owned(),
+                        is_image: true,
+                    }
+                }
+                _ => Span::Image(value.to_owned()),
+            }
+        }
+        "gps" => {
+            let mut parts = value.split(',');
+            let x = parts.next()?.trim().parse().ok()?;
+            let y = parts.next()?.trim().parse().ok()?;
+            let surface = parts.next().map(|surface| surface.trim().to_owned());
+            if parts.next().is_some() {
+                return None;
+            }
+            Span::Gps { x, y, surface }
+        }
+        _ if PROTOTYPE_TAGS.contains(&tag) => {
+            Span::Prototype {
+                category: tag.to_owned(),
+                name: value.to_owned(),
+                is_image: false,
+            }
+        }
+        _ if OTHER_TAGS.contains(&tag) => {
+            Span::Tag {
+                tag: tag.to_owned(),
+                value: value.to_owned(),
+            }
+        }
+        _ => return None,
+    };
+
+    Some(Tag::Span(span))
+}
+
+/// The spans of the innermost open tag, or the top-level spans.
+fn current<'a>(
+    stack: &'a mut [(Container, Vec<Span>)],
+    spans: &'a mut Vec<Span>,
+) -> &'a mut Vec<Span> {
+    stack.last_mut().map_or(spans, |(_, spans)| spans)
+}
+
+fn push_text(spans: &mut Vec<Span>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(Span::Text(last)) = spans.last_mut() {
+        last.push_str(text);
+    }
+    else {
+        spans.push(Span::Text(text.to_owned()));
+    }
+}
+
+fn visit<F: FnMut(&Span)>(spans: &[Span], f: &mut F) {
+    for span in spans {
+        f(span);
+        if let Span::Color { spans, .. } | Span::Font { spans, .. } = span {
+            visit(spans, f);
+        }
+    }
+}
+
+fn is_bold(font: &str) -> bool {
+    font.contains("bold")
+}
+
+fn format_gps(x: f64, y: f64, surface: &Option<String>) -> String {
+    match surface {
+        Some(surface) => format!("{}, {} ({})", x, y, surface),
+        None => format!("{}, {}", x, y),
+    }
+}
+
+fn render_plain<R: Resolver + ?Sized>(spans: &[Span], resolver: &R, output: &mut String) {
+    for span in spans {
+        match span {
+            Span::Text(text) => output.push_str(text),
+            Span::Color { spans, .. } | Span::Font { spans, .. } => {
+                render_plain(spans, resolver, output)
+            }
+            Span::Prototype {
+                category,
+                name,
+                is_image: false,
+            } => output.push_str(&resolver.name(category, name)),
+            Span::Prototype { is_image: true, .. } | Span::Image(_) => {}
+            Span::Gps { x, y, surface } => output.push_str(&format_gps(*x, *y, surface)),
+            Span::Tag { value, .. } => output.push_str(value),
+        }
+    }
+}
+
+/// The style of ANSI output, as a stack of colors and whether the font is
+/// bold.
+type AnsiStyle = Vec<(Option<[u8; 3]>, bool)>;
+
+fn apply_ansi_style(style: &AnsiStyle, output: &mut String) {
+    output.push_str("\x1b[0m");
+    if style.iter().any(|(_, bold)| *bold) {
+        output.push_str("\x1b[1m");
+    }
+    if let Some([r, g, b]) = style.iter().rev().find_map(|(color, _)| *color) {
+        let _ = write!(output, "\x1b[38;2;{};{};{}m", r, g, b);
+    }
+}
+
+fn render_ansi<R: Resolver + ?Sized>(
+    spans: &[Span],
+    resolver: &R,
+    style: &mut AnsiStyle,
+    output: &mut String,
+) {
+    for span in spans {
+        match span {
+            Span::Color { color, spans } => {
+                style.push((parse_color(color), false));
+                apply_ansi_style(style, output);
+                render_ansi(spans, resolver, style, output);
+                style.pop();
+                apply_ansi_style(style, output);
+            }
+            Span::Font { font, spans } => {
+                style.push((None, is_bold(font)));
+                apply_ansi_style(style, output);
+                render_ansi(spans, resolver, style, output);
+                style.pop();
+                apply_ansi_style(style, output);
+            }
+            span => render_plain(std::slice::from_ref(span), resolver, output),
+        }
+    }
+}
+
+fn escape_html(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn render_html<R, I>(spans: &[Span], resolver: &R, icon_url: &I, output: &mut String)
+where
+    R: Resolver + ?Sized,
+    I: Fn(&str, &str) -> Option<String>,
+{
+    for span in spans {
+        match span {
+            Span::Text(text) => escape_html(text, output),
+            Span::Color { color, spans } => {
+                match parse_color(color) {
+                    Some([r, g, b]) => {
+                        let _ = write!(
+                            output,
+                            r#"<span style="color: #{:02x}{:02x}{:02x}">"#,
+                            r, g, b
+                        );
+                    }
+                    None => output.push_str("<span>"),
+                }
+                render_html(spans, resolver, icon_url, output);
+                output.push_str("</span>");
+            }
+            Span::Font { font, spans } => {
+                output.push_str(r#"<span class="font-"#);
+                escape_html(font, output);
+                output.push('"');
+                if is_bold(font) {
+                    output.push_str(r#" style="font-weight: bold""#);
+                }
+                output.push('>');
+                render_html(spans, resolver, icon_url, output);
+                output.push_str("</span>");
+            }
+            Span::Prototype {
+                category,
+                name,
+                is_image,
+            } => {
+                let localised_name = resolver.name(category, name);
+                match icon_url(category, name) {
+                    Some(url) => {
+                        output.push_str(r#"<img class="icon" src=""#);
+                        escape_html(&url, output);
+                        output.push_str(r#"" alt=""#);
+                        escape_html(&localised_name, output);
+                        output.push_str(r#"" title=""#);
+                        escape_html(&localised_name, output);
+                        output.push_str(r#"">"#);
+                    }
+                    None if !is_image => escape_html(&localised_name, output),
+                    None => {}
+                }
+            }
+            Span::Image(_) => {}
+            Span::Gps { x, y, surface } => {
+                output.push_str(r#"<span class="gps">"#);
+                escape_html(&format_gps(*x, *y, surface), output);
+                output.push_str("</span>");
+            }
+            Span::Tag { value, .. } => escape_html(value, output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_rich_text() {
+        let input = "[item=iron-plate] [color=red]Unload [font=default-bold]now[/color] at [gps=12.5,-3] [img=utility/warning_icon][/font] [x]";
+        let rich_text = RichText::parse(input);
+
+        assert_eq!(
+            rich_text.spans,
+            vec![
+                Span::Prototype {
+                    category: "item".to_owned(),
+                    name: "iron-plate".to_owned(),
+                    is_image: false,
+                },
+                Span::Text(" ".to_owned()),
+                Span::Color {
+                    color: "red".to_owned(),
+                    spans: vec![
+                        Span::Text("Unload ".to_owned()),
+                        Span::Font {
+                            font: "default-bold".to_owned(),
+                            spans: vec![Span::Text("now".to_owned())],
+                        },
+                    ],
+                },
+                Span::Text(" at ".to_owned()),
+                Span::Gps {
+                    x: 12.5,
+                    y: -3.0,
+                    surface: None,
+                },
+                Span::Text(" ".to_owned()),
+                Span::Image("utility/warning_icon".to_owned()),
+                Span::Text("[/font] [x]".to_owned()),
+            ]
+        );
+
+        let names = |_: &str, name: &str| name.to_uppercase();
+        assert_eq!(
+            rich_text.to_plain(&names),
+            "IRON-PLATE Unload now at 12.5, -3 [/font] [x]"
+        );
+        assert_eq!(
+            RichText::parse("[color=1,0,0]<b>[/color]").to_html(&names, &|_: &str, _: &str| None),
+            r#"<span style="color: #ff0000">&lt;b&gt;</span>"#
+        );
+        assert_eq!(
+            RichText::parse(&rich_text.to_string()).spans,
+            rich_text.spans
+        );
+    }
+
+    #[test]
+    fn it_parses_colors() {
+        assert_eq!(parse_color("#ff8000"), Some([255, 128, 0]));
+        assert_eq!(parse_color("1,0.5,0"), Some([255, 128, 0]));
+        assert_eq!(parse_color("255, 128, 0"), Some([255, 128, 0]));
+        assert_eq!(parse_color("red"), Some([255, 42, 36]));
+        assert_eq!(parse_color("nope"), None);
+    }
+}
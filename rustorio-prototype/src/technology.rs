@@ -17,6 +17,7 @@ use super::{
     recipe::IngredientPrototype,
     Inherits,
     InheritsBase,
+    Prototype,
     PrototypeBase,
 };
 use crate::types::{
@@ -24,9 +25,10 @@ use crate::types::{
     IconSpecification,
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Prototype)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[prototype(type_name = "technology")]
 pub struct TechnologyPrototype {
     #[cfg_attr(feature = "lua-api", lua(flatten))]
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -738,6 +738,7 @@ pub type DamageType = Todo;
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AutoplaceSpecification {
+    #[cfg_attr(feature = "lua-api", lua(default))]
     pub control: Option<Id<AutoplaceControl>>,
 
     #[cfg_attr(feature = "lua-api", lua(default_with = "true"))]
@@ -755,9 +756,11 @@ pub struct AutoplaceSpecification {
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub tile_restrictions: Vec<TileRestriction>,
 
-    pub probability_expression: NoiseExpression,
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    pub probability_expression: Option<NoiseExpression>,
 
-    pub richness_expression: NoiseExpression,
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    pub richness_expression: Option<NoiseExpression>,
     // TODO: peak-based
 }
 
@@ -1641,7 +1644,7 @@ pub struct FluidBox {
     pub height: f64,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
-    pub filter: Option<Id<Fluid>>,
+    pub filter: Option<Id<FluidPrototype>>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub render_layer: RenderLayer,
@@ -1652,9 +1655,11 @@ pub struct FluidBox {
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub pipe_picture: Option<Sprite4Way>,
 
-    pub minimum_temperature: f64,
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    pub minimum_temperature: Option<f64>,
 
-    pub maximum_temperature: f64,
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    pub maximum_temperature: Option<f64>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub production_type: Option<ProductionType>,
@@ -1666,8 +1671,6 @@ pub struct FluidBox {
     pub secondary_draw_orders: FourWay<i8>,
 }
 
-pub type Fluid = Todo;
-
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaValue))]
 #[cfg_attr(
@@ -1694,6 +1697,7 @@ pub struct PipeConnectionDefinition {
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub max_underground_distance: u32,
 
+    #[cfg_attr(feature = "lua-api", lua(default))]
     pub r#type: Option<ProductionType>,
 }
 
@@ -1750,6 +1754,33 @@ impl IconSpecification {
             IconSpecification::None => None,
         }
     }
+
+    /// A key that identifies how the icon looks, e.g. to look up exported
+    /// icons. Icons that are a single untinted and unshifted image use its file
+    /// name.
+    pub fn key(&self) -> Option<String> {
+        match self {
+            IconSpecification::Multiple {
+                icons, icon_size, ..
+            } => {
+                match icons.as_slice() {
+                    [] => None,
+                    [layer] if layer.is_untinted() && layer.is_unshifted() => {
+                        Some(layer.icon.to_string())
+                    }
+                    layers => {
+                        let keys = layers
+                            .iter()
+                            .map(|layer| layer.key(layer.icon_size.or(*icon_size)))
+                            .collect::<Vec<_>>();
+                        Some(keys.join(";"))
+                    }
+                }
+            }
+            IconSpecification::Single { icon, .. } => Some(icon.to_string()),
+            IconSpecification::None => None,
+        }
+    }
 }
 
 #[cfg(feature = "lua-api")]
@@ -1799,19 +1830,55 @@ pub struct IconData {
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub icon_size: Option<SpriteSizeType>,
 
-    #[cfg_attr(feature = "lua-api", lua(default_with = "Color::new(0., 0., 0., 1.)"))]
+    #[cfg_attr(feature = "lua-api", lua(default_with = "Color::new(1., 1., 1., 1.)"))]
     pub tint: Color,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub shift: Vector2<f32>,
 
-    #[cfg_attr(feature = "lua-api", lua(default_with = "1."))]
-    pub scale: f64,
+    /// Defaults to `32 / icon_size` for most prototypes and `256 / icon_size`
+    /// for technologies, i.e. the layer is shown as large as a plain icon.
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub scale: Option<f64>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     pub icon_mipmaps: u8,
 }
 
+impl IconData {
+    pub fn is_untinted(&self) -> bool {
+        let tint = &self.tint;
+        [
+            tint.color.red,
+            tint.color.green,
+            tint.color.blue,
+            tint.alpha,
+        ] == [1.0; 4]
+    }
+
+    pub fn is_unshifted(&self) -> bool {
+        self.shift.x == 0.0 && self.shift.y == 0.0
+    }
+
+    fn key(&self, icon_size: Option<SpriteSizeType>) -> String {
+        let tint = &self.tint;
+        format!(
+            "{}|size={}|scale={}|shift={},{}|tint={},{},{},{}",
+            self.icon,
+            icon_size.unwrap_or_default(),
+            self.scale
+                .map_or_else(|| "default".to_owned(), |scale| scale.to_string()),
+            self.shift.x,
+            self.shift.y,
+            tint.color.red,
+            tint.color.green,
+            tint.color.blue,
+            tint.alpha,
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -2009,29 +2076,30 @@ impl<I: FromLuaTable + From<(Id<ItemPrototype>, u16)>, F: FromLuaTable> FromLuaT
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MinableProperties {
-    mining_time: f64,
+    pub mining_time: f64,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     #[cfg_attr(feature = "serde", serde(default))]
-    results: Vec<ProductPrototype>,
+    pub results: Vec<ProductPrototype>,
 
-    result: Option<Id<ItemPrototype>>,
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    pub result: Option<Id<ItemPrototype>>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
     #[cfg_attr(feature = "serde", serde(default))]
-    fluid_amount: f64,
+    pub fluid_amount: f64,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
-    mining_particle: Option<Id<Todo>>,
+    pub mining_particle: Option<Id<Todo>>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
-    required_fluid: Option<Id<FluidPrototype>>,
+    pub required_fluid: Option<Id<FluidPrototype>>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
-    count: Option<u16>,
+    pub count: Option<u16>,
 
     #[cfg_attr(feature = "lua-api", lua(default))]
-    mining_trigger: Option<Trigger>,
+    pub mining_trigger: Option<Trigger>,
 }
 
 #[derive(Clone, Debug)]
@@ -2039,10 +2107,15 @@ pub struct MinableProperties {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MiningDrillGraphicsSet();
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct ModuleSpecification();
+pub struct ModuleSpecification {
+    #[cfg_attr(feature = "lua-api", lua(default))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub module_slots: ItemStackIndex,
+    // todo: the options for drawing the module icons, e.g. `module_info_icon_shift`
+}
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "lua-api", derive(FromLuaTable))]
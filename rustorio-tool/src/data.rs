@@ -13,12 +13,47 @@ use crate::error::Error;
 pub struct GameData {
     pub prototypes: Prototypes,
     pub icons: HashMap<String, String>,
+    #[serde(default)]
+    pub atlases: Vec<Atlas>,
 }
 
 impl GameData {
-    pub fn icon(&self, path: &str) -> Option<&str> {
-        self.icons.get(path).map(|s| s.as_str())
+    pub fn icon(&self, key: &str) -> Option<&str> {
+        self.icons.get(key).map(|s| s.as_str())
     }
+
+    /// The atlas in which to look up icons that are shown in the given size:
+    /// the smallest one that is at least as large, or the largest one.
+    pub fn atlas(&self, size: u32) -> Option<&Atlas> {
+        self.atlases
+            .iter()
+            .filter(|atlas| atlas.size >= size)
+            .min_by_key(|atlas| atlas.size)
+            .or_else(|| self.atlases.iter().max_by_key(|atlas| atlas.size))
+    }
+}
+
+/// Icons packed into sprite sheets by `rustorio-export --atlas-size`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Atlas {
+    pub size: u32,
+    pub sheets: Vec<AtlasSheet>,
+    pub icons: HashMap<String, AtlasIcon>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AtlasSheet {
+    /// The path of the image, relative to the data directory.
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct AtlasIcon {
+    pub sheet: usize,
+    pub x: u32,
+    pub y: u32,
 }
 
 #[derive(Debug)]
@@ -83,15 +83,15 @@ fn App() -> Html {
                                 {"Home"}
                             </NavItem>
                             <NavItem route={Route::Research}>
-                                <FactorioIcon file_name="__base__/graphics/icons/lab.png" class="nav-icon" />
+                                <FactorioIcon icon="__base__/graphics/icons/lab.png" class="nav-icon" />
                                 {"Research"}
                             </NavItem>
                             <NavItem route={Route::Production}>
-                                <FactorioIcon file_name="__base__/graphics/icons/assembling-machine-3.png" class="nav-icon" />
+                                <FactorioIcon icon="__base__/graphics/icons/assembling-machine-3.png" class="nav-icon" />
                                 {"Production"}
                             </NavItem>
                             <NavItem route={Route::Blueprints}>
-                                <FactorioIcon file_name="__base__/graphics/icons/assembling-machine-3.png" class="nav-icon" />
+                                <FactorioIcon icon="__base__/graphics/icons/assembling-machine-3.png" class="nav-icon" />
                                 {"Blueprints"}
                             </NavItem>
                         </ul>
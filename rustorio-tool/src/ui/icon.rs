@@ -12,7 +12,9 @@ use crate::AppData;
 
 #[derive(PartialEq, Properties)]
 pub struct IconProps {
-    pub file_name: String,
+    /// The key of the icon, see `IconSpecification::key`. This is the file name
+    /// for icons with a single layer.
+    pub icon: String,
     pub alt: Option<AttrValue>,
     #[prop_or_default]
     pub class: Classes,
@@ -22,18 +24,40 @@ pub struct IconProps {
 #[function_component]
 pub fn FactorioIcon(
     IconProps {
-        file_name,
+        icon,
         alt,
         class,
         size,
     }: &IconProps,
 ) -> Html {
     let data = use_context::<AppData>().expect("no ctx found");
+    let game_data = data.game_data();
 
-    let src = data
-        .game_data()
-        .icon(file_name)
-        .map(|s| format!("/data/icons/{s}"));
+    // Icons from an atlas are shown as the background of an element of the
+    // requested size, with the sheet scaled accordingly.
+    let shown_size = size
+        .as_ref()
+        .and_then(|size| size.parse::<u32>().ok())
+        .unwrap_or(32);
+    if let Some((atlas, icon, sheet)) = game_data.atlas(shown_size).and_then(|atlas| {
+        let icon = atlas.icons.get(icon)?;
+        Some((atlas, icon, atlas.sheets.get(icon.sheet)?))
+    }) {
+        let scale = f64::from(shown_size) / f64::from(atlas.size);
+        let style = format!(
+            "display: inline-block; width: {shown_size}px; height: {shown_size}px; background-image: url(\"/data/{}\"); background-position: -{}px -{}px; background-size: {}px {}px; background-repeat: no-repeat;",
+            sheet.file_name,
+            f64::from(icon.x) * scale,
+            f64::from(icon.y) * scale,
+            f64::from(sheet.width) * scale,
+            f64::from(sheet.height) * scale,
+        );
+        return html! {
+            <span role="img" aria-label={alt.clone()} title={alt.clone()} class={class.clone()} {style}></span>
+        };
+    }
+
+    let src = game_data.icon(icon).map(|s| format!("/data/icons/{s}"));
 
     html! { <img {src} {alt} class={class.clone()} width={size} /> }
 }
@@ -173,8 +173,8 @@ impl SelectableItem for LabPrototype {
             .parent
             .parent
             .icon_spec
-            .file_name()
-            .map(|file_name| html! { <FactorioIcon file_name={file_name.to_string()} size="32" /> })
+            .key()
+            .map(|icon| html! { <FactorioIcon {icon} size="32" /> })
             .unwrap_or_default()
     }
 
@@ -206,9 +206,10 @@ impl SelectableItem for TechnologyPrototype {
     }
 
     fn icon(&self) -> Html {
-        let icon = self.icon_spec.file_name().map(
-            |file_name| html! { <FactorioIcon file_name={file_name.to_string()} size="32" /> },
-        );
+        let icon = self
+            .icon_spec
+            .key()
+            .map(|icon| html! { <FactorioIcon {icon} size="32" /> });
         html! {
             <>
                 { icon.unwrap_or_default() }